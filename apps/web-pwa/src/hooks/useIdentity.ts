@@ -311,7 +311,8 @@ function buildAttestation(): IdentityRecord['attestation'] {
     platform: 'web',
     integrityToken: randomToken(),
     deviceKey: randomToken(),
-    nonce: randomToken()
+    // The verifier requires a hex nonce; strip the UUID separators.
+    nonce: randomToken().replace(/-/g, '')
   };
 }
 
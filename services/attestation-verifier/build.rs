@@ -0,0 +1,49 @@
+//! Bakes the git commit and build time into the binary for `/health`.
+//!
+//! The commit comes from `GIT_COMMIT` (set by the Docker build, which has no
+//! `.git`) or `git rev-parse HEAD`; the build time honours
+//! `SOURCE_DATE_EPOCH` for reproducible builds.
+
+use std::env;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let commit = env::var("GIT_COMMIT")
+        .ok()
+        .filter(|c| !c.trim().is_empty())
+        .or_else(git_head)
+        .unwrap_or_else(|| "unknown".to_string());
+    let built_at = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+        });
+    println!("cargo:rustc-env=VERIFIER_GIT_COMMIT={}", commit.trim());
+    println!("cargo:rustc-env=VERIFIER_BUILD_TIMESTAMP={built_at}");
+
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=src");
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        println!("cargo:rerun-if-changed={git_dir}/HEAD");
+        println!("cargo:rerun-if-changed={git_dir}/refs/heads");
+    }
+}
+
+fn git_head() -> Option<String> {
+    git(&["rev-parse", "HEAD"])
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
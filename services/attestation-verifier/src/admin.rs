@@ -0,0 +1,122 @@
+//! Operator API under `/admin`.
+//!
+//! Disabled (every `/admin` path is a 404) unless `ADMIN_TOKEN` is set to a
+//! secret of at least `MIN_TOKEN_LEN` characters.  Calls authenticate with
+//! `Authorization: Bearer <token>`.  Keep `/admin` off the public proxy
+//! route; the token is the only gate once a request reaches the service.
+//!
+//! Every admin call — including refused ones — is written to the audit log
+//! (see `audit`) with the acting token's fingerprint, never the token itself.
+//! Calls spend a client-IP rate-limit token before authentication, which
+//! bounds how fast refusals can be appended.
+
+use std::env;
+
+use sha2::{Digest, Sha256};
+
+/// Shortest accepted `ADMIN_TOKEN`.
+pub const MIN_TOKEN_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denied {
+    /// No `ADMIN_TOKEN`: the API does not exist.
+    Disabled,
+    MissingCredentials,
+    BadCredentials,
+}
+
+impl Denied {
+    pub fn code(self) -> &'static str {
+        match self {
+            Denied::Disabled => "NOT_FOUND",
+            Denied::MissingCredentials => "ADMIN_CREDENTIALS_REQUIRED",
+            Denied::BadCredentials => "ADMIN_CREDENTIALS_INVALID",
+        }
+    }
+}
+
+/// Who made an admin call: `admin:<fingerprint of the token>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operator(pub String);
+
+pub struct AdminAuth {
+    /// SHA-256 of the token, so comparisons do not leak its prefix.
+    token_digest: Option<[u8; 32]>,
+}
+
+impl AdminAuth {
+    pub fn new(token: Option<&str>) -> Result<Self, String> {
+        let token_digest = match token.map(str::trim) {
+            Some(token) if token.len() < MIN_TOKEN_LEN => {
+                return Err(format!(
+                    "ADMIN_TOKEN must be at least {MIN_TOKEN_LEN} characters"
+                ));
+            }
+            Some(token) => Some(Sha256::digest(token.as_bytes()).into()),
+            None => None,
+        };
+        Ok(Self { token_digest })
+    }
+
+    pub fn disabled() -> Self {
+        Self { token_digest: None }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        Self::new(env::var("ADMIN_TOKEN").ok().as_deref())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.token_digest.is_some()
+    }
+
+    /// Check an `Authorization` header value.
+    pub fn authorize(&self, authorization: Option<&str>) -> Result<Operator, Denied> {
+        let Some(expected) = &self.token_digest else {
+            return Err(Denied::Disabled);
+        };
+        let token = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or(Denied::MissingCredentials)?;
+        let presented: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        if presented != *expected {
+            return Err(Denied::BadCredentials);
+        }
+        Ok(Operator(format!("admin:{}", hex::encode(&presented[..6]))))
+    }
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn bearer_token_is_required() {
+        let auth = AdminAuth::new(Some(TOKEN)).unwrap();
+        let operator = auth.authorize(Some(&format!("Bearer {TOKEN}"))).unwrap();
+        assert!(operator.0.starts_with("admin:"));
+        assert!(!operator.0.contains(TOKEN));
+        assert_eq!(auth.authorize(None), Err(Denied::MissingCredentials));
+        assert_eq!(auth.authorize(Some(TOKEN)), Err(Denied::MissingCredentials));
+        assert_eq!(
+            auth.authorize(Some("Bearer 0123456789abcdef0123456789abcdeX")),
+            Err(Denied::BadCredentials)
+        );
+    }
+
+    #[test]
+    fn unset_token_disables_the_api() {
+        let auth = AdminAuth::new(None).unwrap();
+        assert!(!auth.is_enabled());
+        assert_eq!(
+            auth.authorize(Some(&format!("Bearer {TOKEN}"))),
+            Err(Denied::Disabled)
+        );
+        assert!(AdminAuth::new(Some("short")).is_err());
+    }
+}
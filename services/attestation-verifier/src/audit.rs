@@ -0,0 +1,450 @@
+//! Tamper-evident audit log.
+//!
+//! Every issued session, session revocation and admin call is appended as
+//! one NDJSON line to `$VERIFIER_STATE_DIR/audit.ndjson` (memory-only
+//! without it).  Each entry commits to its predecessor:
+//!
+//! ```text
+//! hash = hex(SHA-256(JSON {seq, ts, prev, event}))    prev = previous hash
+//! ```
+//!
+//! so editing, dropping or reordering any entry breaks every later hash.
+//! Identifiers (nullifiers, jtis) are stored only as SHA-256 hashes; hash a
+//! known identifier with `hash_id` to find its entries.  The chain is
+//! checked on startup and by `attestation-verifier audit verify`.
+
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::admin::Operator;
+
+/// `prev` of the first entry.
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Entries kept when memory-only.
+const MEMORY_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEvent {
+    SessionIssued {
+        jti_hash: String,
+        nullifier_hash: String,
+        platform: String,
+        trust_score: f32,
+        assurance: String,
+        policy_version: String,
+        kid: String,
+        mock: bool,
+    },
+    SessionsRevoked {
+        operator: String,
+        /// `nullifier` or `jti`.
+        by: String,
+        subject_hash: String,
+    },
+    AdminCall {
+        operator: String,
+        action: String,
+        outcome: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        subject_hash: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+}
+
+impl AuditEvent {
+    fn kind(&self) -> &'static str {
+        match self {
+            AuditEvent::SessionIssued { .. } => "session_issued",
+            AuditEvent::SessionsRevoked { .. } => "sessions_revoked",
+            AuditEvent::AdminCall { .. } => "admin_call",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    /// Unix seconds.
+    pub ts: u64,
+    pub prev: String,
+    pub event: AuditEvent,
+    pub hash: String,
+}
+
+/// The hashed part of an entry; field order is part of the format.
+#[derive(Serialize)]
+struct Body<'a> {
+    seq: u64,
+    ts: u64,
+    prev: &'a str,
+    event: &'a AuditEvent,
+}
+
+impl AuditEntry {
+    fn seal(seq: u64, ts: u64, prev: String, event: AuditEvent) -> Self {
+        let hash = body_hash(seq, ts, &prev, &event);
+        Self {
+            seq,
+            ts,
+            prev,
+            event,
+            hash,
+        }
+    }
+
+    fn is_sealed(&self) -> bool {
+        body_hash(self.seq, self.ts, &self.prev, &self.event) == self.hash
+    }
+}
+
+fn body_hash(seq: u64, ts: u64, prev: &str, event: &AuditEvent) -> String {
+    let body = serde_json::to_vec(&Body {
+        seq,
+        ts,
+        prev,
+        event,
+    })
+    .expect("audit entries serialize");
+    hex::encode(Sha256::digest(body))
+}
+
+/// How identifiers appear in the log.
+pub fn hash_id(value: &str) -> String {
+    hex::encode(Sha256::digest(value.as_bytes()))
+}
+
+struct Chain {
+    next_seq: u64,
+    head: String,
+    /// Recent entries, kept only when memory-only.
+    memory: VecDeque<AuditEntry>,
+}
+
+pub struct AuditLog {
+    chain: Mutex<Chain>,
+    path: Option<PathBuf>,
+}
+
+impl AuditLog {
+    pub fn memory() -> Self {
+        Self {
+            chain: Mutex::new(Chain {
+                next_seq: 0,
+                head: GENESIS.to_string(),
+                memory: VecDeque::new(),
+            }),
+            path: None,
+        }
+    }
+
+    /// Continue the chain in `path`, which must verify.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let summary = match fs::File::open(&path) {
+            Ok(file) => verify_chain(std::io::BufReader::new(file))
+                .map_err(|e| format!("{}: {e}", path.display()))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => ChainSummary::default(),
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        };
+        Ok(Self {
+            chain: Mutex::new(Chain {
+                next_seq: summary.entries,
+                head: summary.head,
+                memory: VecDeque::new(),
+            }),
+            path: Some(path),
+        })
+    }
+
+    /// `$VERIFIER_STATE_DIR/audit.ndjson`, or memory-only when unset.
+    pub fn from_env() -> Result<Self, String> {
+        match default_path() {
+            Some(path) => Self::open(path),
+            None => Ok(Self::memory()),
+        }
+    }
+
+    /// Append `event`.  A write failure is logged, not returned: auditing
+    /// must not take the verifier down, and the gap shows up as a chain
+    /// that stops.
+    pub fn record(&self, event: AuditEvent) {
+        let mut chain = self.chain.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = AuditEntry::seal(chain.next_seq, now(), chain.head.clone(), event);
+        if let Some(path) = &self.path {
+            if let Err(e) = append(path, &entry) {
+                tracing::error!("cannot append to audit log {}: {e}", path.display());
+                return;
+            }
+        } else {
+            if chain.memory.len() == MEMORY_CAPACITY {
+                chain.memory.pop_front();
+            }
+            chain.memory.push_back(entry.clone());
+        }
+        tracing::info!(
+            target: "audit",
+            seq = entry.seq,
+            kind = entry.event.kind(),
+            hash = %entry.hash,
+            "audit record"
+        );
+        chain.next_seq += 1;
+        chain.head = entry.hash;
+    }
+
+    /// Record an admin call.  `subject` is stored hashed; `detail` must
+    /// not contain identifiers.
+    pub fn admin_call(
+        &self,
+        operator: Option<&Operator>,
+        action: &str,
+        outcome: &str,
+        subject: Option<&str>,
+        detail: Option<String>,
+    ) {
+        self.record(AuditEvent::AdminCall {
+            operator: operator.map_or("anonymous", |o| o.0.as_str()).to_string(),
+            action: action.to_string(),
+            outcome: outcome.to_string(),
+            subject_hash: subject.map(hash_id),
+            detail,
+        });
+    }
+
+    #[cfg(test)]
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        let chain = self.chain.lock().unwrap_or_else(|e| e.into_inner());
+        chain.memory.iter().cloned().collect()
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var("VERIFIER_STATE_DIR")
+        .ok()
+        .map(|dir| Path::new(&dir).join("audit.ndjson"))
+}
+
+#[tracing::instrument(name = "store.append_audit", skip_all, fields(path = ?path))]
+fn append(path: &Path, entry: &AuditEntry) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut line = serde_json::to_vec(entry).expect("audit entries serialize");
+    line.push(b'\n');
+    // One write per entry, so a crash cannot interleave two.
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(&line)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+// ── verification / export ──────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainSummary {
+    pub entries: u64,
+    pub head: String,
+}
+
+impl Default for ChainSummary {
+    fn default() -> Self {
+        Self {
+            entries: 0,
+            head: GENESIS.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainError {
+    /// 1-based line number.
+    pub line: usize,
+    pub reason: String,
+}
+
+impl std::fmt::Display for ChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "chain broken at line {}: {}", self.line, self.reason)
+    }
+}
+
+/// Check every entry's hash, sequence number and link to its predecessor.
+pub fn verify_chain(reader: impl BufRead) -> Result<ChainSummary, ChainError> {
+    let mut summary = ChainSummary::default();
+    for (index, line) in reader.lines().enumerate() {
+        let broken = |reason: String| ChainError {
+            line: index + 1,
+            reason,
+        };
+        let line = line.map_err(|e| broken(e.to_string()))?;
+        let entry: AuditEntry =
+            serde_json::from_str(&line).map_err(|e| broken(format!("unreadable entry: {e}")))?;
+        if entry.seq != summary.entries {
+            return Err(broken(format!(
+                "expected seq {}, found {}",
+                summary.entries, entry.seq
+            )));
+        }
+        if entry.prev != summary.head {
+            return Err(broken(format!("seq {} does not link to its predecessor", entry.seq)));
+        }
+        if !entry.is_sealed() {
+            return Err(broken(format!("seq {} does not match its hash", entry.seq)));
+        }
+        summary.entries += 1;
+        summary.head = entry.hash;
+    }
+    Ok(summary)
+}
+
+/// Copy entries with `from <= seq <= to` to `out` as NDJSON.  Returns how
+/// many were written.
+pub fn export(
+    reader: impl BufRead,
+    from: u64,
+    to: u64,
+    mut out: impl Write,
+) -> Result<u64, String> {
+    let mut written = 0;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let entry: AuditEntry = serde_json::from_str(&line)
+            .map_err(|e| format!("line {}: unreadable entry: {e}", index + 1))?;
+        if entry.seq > to {
+            break;
+        }
+        if entry.seq >= from {
+            writeln!(out, "{line}").map_err(|e| e.to_string())?;
+            written += 1;
+        }
+    }
+    Ok(written)
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    fn admin_event(action: &str) -> AuditEvent {
+        AuditEvent::AdminCall {
+            operator: "admin:abc".to_string(),
+            action: action.to_string(),
+            outcome: "ok".to_string(),
+            subject_hash: None,
+            detail: None,
+        }
+    }
+
+    fn temp_log() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("audit-{:016x}", rand::random::<u64>()))
+            .join("audit.ndjson")
+    }
+
+    fn read_lines(path: &Path) -> Vec<String> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn verify_lines(lines: &[String]) -> Result<ChainSummary, ChainError> {
+        verify_chain(lines.join("\n").as_bytes())
+    }
+
+    #[test]
+    fn entries_chain_from_genesis() {
+        let log = AuditLog::memory();
+        log.record(admin_event("a"));
+        log.record(admin_event("b"));
+        let entries = log.entries();
+        assert_eq!(entries[0].seq, 0);
+        assert_eq!(entries[0].prev, GENESIS);
+        assert_eq!(entries[1].prev, entries[0].hash);
+        assert!(entries.iter().all(AuditEntry::is_sealed));
+    }
+
+    #[test]
+    fn file_log_survives_reopen_and_verifies() {
+        let path = temp_log();
+        AuditLog::open(&path).unwrap().record(admin_event("a"));
+        let reopened = AuditLog::open(&path).unwrap();
+        reopened.record(admin_event("b"));
+        reopened.admin_call(None, "c", "refused", Some("nullifier-xyz"), None);
+
+        let summary = verify_chain(BufReader::new(fs::File::open(&path).unwrap())).unwrap();
+        assert_eq!(summary.entries, 3);
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("nullifier-xyz"));
+        assert!(text.contains(&hash_id("nullifier-xyz")));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn tampering_breaks_the_chain() {
+        let path = temp_log();
+        let log = AuditLog::open(&path).unwrap();
+        for action in ["a", "b", "c"] {
+            log.record(admin_event(action));
+        }
+        let lines = read_lines(&path);
+        assert!(verify_lines(&lines).is_ok());
+
+        let mut edited = lines.clone();
+        edited[1] = edited[1].replace("\"action\":\"b\"", "\"action\":\"x\"");
+        assert_eq!(verify_lines(&edited).unwrap_err().line, 2);
+
+        let mut dropped = lines.clone();
+        dropped.remove(1);
+        assert!(verify_lines(&dropped).unwrap_err().reason.contains("expected seq 1"));
+
+        let mut swapped = lines.clone();
+        swapped.swap(1, 2);
+        assert!(verify_lines(&swapped).is_err());
+
+        // A broken file refuses to be extended.
+        fs::write(&path, edited.join("\n")).unwrap();
+        assert!(AuditLog::open(&path).is_err());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn exports_ranges_as_ndjson() {
+        let path = temp_log();
+        let log = AuditLog::open(&path).unwrap();
+        for action in ["a", "b", "c", "d"] {
+            log.record(admin_event(action));
+        }
+        let mut out = Vec::new();
+        let file = BufReader::new(fs::File::open(&path).unwrap());
+        assert_eq!(export(file, 1, 2, &mut out).unwrap(), 2);
+        let exported: Vec<AuditEntry> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(exported.iter().map(|e| e.seq).collect::<Vec<_>>(), [1, 2]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
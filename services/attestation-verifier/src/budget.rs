@@ -0,0 +1,498 @@
+//! Per-nullifier daily participation budgets (spec-xp-ledger-v0 §4).
+//!
+//! Server-side twin of `packages/types/src/budget-utils.ts`.  The client
+//! copy can be reset by clearing storage; this one is keyed by the session
+//! nullifier, persisted through the verifier's store and rolls over at
+//! local midnight in `BUDGET_TIMEZONE` (IANA name, default `UTC`).
+//!
+//! `consume` accepts an idempotency key: a retry with the same key and the
+//! same request within `IDEMPOTENCY_WINDOW_SECS` returns the original
+//! outcome instead of consuming twice.
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::sync::Mutex;
+
+use chrono::{TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::store::JsonFile;
+
+/// Idempotency keys are remembered this long.
+pub const IDEMPOTENCY_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema)]
+pub enum ActionKey {
+    #[serde(rename = "posts/day")]
+    Posts,
+    #[serde(rename = "comments/day")]
+    Comments,
+    #[serde(rename = "sentiment_votes/day")]
+    SentimentVotes,
+    #[serde(rename = "governance_votes/day")]
+    GovernanceVotes,
+    #[serde(rename = "moderation/day")]
+    Moderation,
+    #[serde(rename = "analyses/day")]
+    Analyses,
+    #[serde(rename = "civic_actions/day")]
+    CivicActions,
+    #[serde(rename = "shares/day")]
+    Shares,
+}
+
+impl ActionKey {
+    pub const ALL: [ActionKey; 8] = [
+        ActionKey::Posts,
+        ActionKey::Comments,
+        ActionKey::SentimentVotes,
+        ActionKey::GovernanceVotes,
+        ActionKey::Moderation,
+        ActionKey::Analyses,
+        ActionKey::CivicActions,
+        ActionKey::Shares,
+    ];
+
+    /// `SEASON_0_BUDGET_DEFAULTS` daily limits.
+    pub fn daily_limit(self) -> u32 {
+        match self {
+            ActionKey::Posts => 20,
+            ActionKey::Comments => 50,
+            ActionKey::SentimentVotes => 200,
+            ActionKey::GovernanceVotes => 20,
+            ActionKey::Moderation => 10,
+            ActionKey::Analyses => 25,
+            ActionKey::CivicActions => 3,
+            ActionKey::Shares => 10,
+        }
+    }
+
+    /// Only `analyses/day` is capped per topic in Season 0.
+    pub fn per_topic_cap(self) -> Option<u32> {
+        match self {
+            ActionKey::Analyses => Some(5),
+            _ => None,
+        }
+    }
+}
+
+/// Result of a check or consume, mirroring `BudgetCheckResult` plus the
+/// counters behind it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetOutcome {
+    pub allowed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub action_key: ActionKey,
+    /// Budget day, `YYYY-MM-DD` in the configured timezone.
+    pub date: String,
+    /// Count for the day after this request.
+    pub count: u32,
+    pub daily_limit: u32,
+    pub remaining: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_topic_cap: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetError {
+    /// Idempotency key already used for a different request.
+    IdempotencyConflict,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Ledger {
+    nullifiers: HashMap<String, NullifierLedger>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct NullifierLedger {
+    date: String,
+    usage: BTreeMap<ActionKey, DailyUsage>,
+    receipts: HashMap<String, Receipt>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DailyUsage {
+    count: u32,
+    topic_counts: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Receipt {
+    recorded_at: u64,
+    action_key: ActionKey,
+    amount: u32,
+    topic_id: Option<String>,
+    outcome: BudgetOutcome,
+}
+
+pub struct BudgetLedger {
+    ledger: Mutex<Ledger>,
+    file: JsonFile,
+    timezone: Tz,
+}
+
+impl BudgetLedger {
+    pub fn new(file: JsonFile, timezone: Tz) -> Result<Self, String> {
+        Ok(Self {
+            ledger: Mutex::new(file.load()?),
+            file,
+            timezone,
+        })
+    }
+
+    /// Memory-only, UTC days.
+    pub fn memory() -> Self {
+        Self::new(JsonFile::memory(), Tz::UTC).unwrap()
+    }
+
+    /// `BUDGET_TIMEZONE` and `$VERIFIER_STATE_DIR/budgets.json`.
+    pub fn from_env() -> Result<Self, String> {
+        let timezone = match env::var("BUDGET_TIMEZONE") {
+            Ok(name) => name
+                .trim()
+                .parse::<Tz>()
+                .map_err(|e| format!("BUDGET_TIMEZONE: {e}"))?,
+            Err(_) => Tz::UTC,
+        };
+        Self::new(JsonFile::from_env("budgets.json"), timezone)
+    }
+
+    /// Whether the ledger survives a restart.
+    pub fn is_persistent(&self) -> bool {
+        self.file.path().is_some()
+    }
+
+    pub fn store(&self) -> &JsonFile {
+        &self.file
+    }
+
+    /// Write the current ledger to the store, e.g. before shutting down.
+    pub fn flush(&self) -> Result<(), String> {
+        let ledger = self.ledger.lock().unwrap_or_else(|e| e.into_inner());
+        self.file.save(&*ledger)
+    }
+
+    /// Budget day containing `now` (unix seconds).
+    pub fn day(&self, now: u64) -> String {
+        Utc.timestamp_opt(now as i64, 0)
+            .single()
+            .unwrap_or_default()
+            .with_timezone(&self.timezone)
+            .date_naive()
+            .to_string()
+    }
+
+    /// Would consuming `amount` be allowed?  Changes nothing.
+    pub fn check(
+        &self,
+        nullifier: &str,
+        key: ActionKey,
+        amount: u32,
+        topic_id: Option<&str>,
+        now: u64,
+    ) -> BudgetOutcome {
+        let today = self.day(now);
+        let ledger = self.ledger.lock().unwrap_or_else(|e| e.into_inner());
+        let usage = ledger
+            .nullifiers
+            .get(nullifier)
+            .filter(|n| n.date == today)
+            .and_then(|n| n.usage.get(&key));
+        evaluate(usage, key, amount, topic_id, today)
+    }
+
+    /// Consume `amount` if allowed.  Returns the outcome and whether it was
+    /// replayed from an earlier request with the same idempotency key.
+    pub fn consume(
+        &self,
+        nullifier: &str,
+        key: ActionKey,
+        amount: u32,
+        topic_id: Option<&str>,
+        idempotency_key: Option<&str>,
+        now: u64,
+    ) -> Result<(BudgetOutcome, bool), BudgetError> {
+        let today = self.day(now);
+        let mut ledger = self.ledger.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = ledger.nullifiers.entry(nullifier.to_string()).or_default();
+        entry
+            .receipts
+            .retain(|_, r| now < r.recorded_at + IDEMPOTENCY_WINDOW_SECS);
+        if let Some(receipt) = idempotency_key.and_then(|k| entry.receipts.get(k)) {
+            if receipt.action_key != key
+                || receipt.amount != amount
+                || receipt.topic_id.as_deref() != topic_id
+            {
+                return Err(BudgetError::IdempotencyConflict);
+            }
+            return Ok((receipt.outcome.clone(), true));
+        }
+        if entry.date != today {
+            entry.date = today.clone();
+            entry.usage.clear();
+        }
+
+        let outcome = evaluate(entry.usage.get(&key), key, amount, topic_id, today);
+        if !outcome.allowed {
+            return Ok((outcome, false));
+        }
+        let usage = entry.usage.entry(key).or_default();
+        usage.count = usage.count.saturating_add(amount);
+        if let (Some(topic), Some(_)) = (topic_id, key.per_topic_cap()) {
+            let count = usage.topic_counts.entry(topic.to_string()).or_default();
+            *count = count.saturating_add(amount);
+        }
+        if let Some(idempotency_key) = idempotency_key {
+            entry.receipts.insert(
+                idempotency_key.to_string(),
+                Receipt {
+                    recorded_at: now,
+                    action_key: key,
+                    amount,
+                    topic_id: topic_id.map(str::to_string),
+                    outcome: outcome.clone(),
+                },
+            );
+        }
+
+        // Yesterday's counters with no live receipts are dead weight.
+        let today = &outcome.date;
+        ledger
+            .nullifiers
+            .retain(|_, n| &n.date == today || !n.receipts.is_empty());
+        if let Err(e) = self.file.save(&*ledger) {
+            tracing::error!("cannot persist budget ledger: {e}");
+        }
+        Ok((outcome, false))
+    }
+}
+
+/// `canConsumeBudget`, reporting the counters as they would be afterwards.
+fn evaluate(
+    usage: Option<&DailyUsage>,
+    key: ActionKey,
+    amount: u32,
+    topic_id: Option<&str>,
+    date: String,
+) -> BudgetOutcome {
+    let daily_limit = key.daily_limit();
+    let current = usage.map_or(0, |u| u.count);
+    let topic = topic_id.filter(|t| !t.is_empty());
+    let per_topic_cap = topic.and(key.per_topic_cap());
+    let current_topic = match (topic, per_topic_cap) {
+        (Some(t), Some(_)) => Some(usage.and_then(|u| u.topic_counts.get(t)).copied().unwrap_or(0)),
+        _ => None,
+    };
+
+    // Saturating, so a huge `amount` is refused rather than wrapping to 0.
+    let reason = if current.saturating_add(amount) > daily_limit {
+        Some(format!(
+            "Daily limit of {daily_limit} reached for {}",
+            key_name(key)
+        ))
+    } else {
+        match (per_topic_cap, current_topic) {
+            (Some(cap), Some(count)) if count.saturating_add(amount) > cap => Some(format!(
+                "Per-topic cap of {cap} reached for {} on topic {}",
+                key_name(key),
+                topic.unwrap_or_default()
+            )),
+            _ => None,
+        }
+    };
+    let allowed = reason.is_none();
+    let spent = if allowed { amount } else { 0 };
+    BudgetOutcome {
+        allowed,
+        reason,
+        action_key: key,
+        date,
+        count: current + spent,
+        daily_limit,
+        remaining: daily_limit.saturating_sub(current + spent),
+        topic_id: topic.map(str::to_string),
+        topic_count: current_topic.map(|c| c.saturating_add(spent)),
+        per_topic_cap,
+    }
+}
+
+fn key_name(key: ActionKey) -> String {
+    serde_json::to_value(key)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2027-01-15T12:00:00Z
+    const NOON_UTC: u64 = 1_800_014_400;
+    const HOUR: u64 = 60 * 60;
+
+    fn ledger() -> BudgetLedger {
+        BudgetLedger::memory()
+    }
+
+    #[test]
+    fn limits_match_season_0_defaults() {
+        let keys: Vec<ActionKey> = serde_json::from_str(
+            r#"["posts/day","comments/day","sentiment_votes/day","governance_votes/day",
+                "moderation/day","analyses/day","civic_actions/day","shares/day"]"#,
+        )
+        .unwrap();
+        let limits: Vec<u32> = keys.iter().map(|k| k.daily_limit()).collect();
+        assert_eq!(limits, [20, 50, 200, 20, 10, 25, 3, 10]);
+        assert_eq!(ActionKey::Analyses.per_topic_cap(), Some(5));
+        assert!(keys.iter().filter(|k| k.per_topic_cap().is_some()).count() == 1);
+    }
+
+    #[test]
+    fn consumes_until_the_daily_limit() {
+        let ledger = ledger();
+        for i in 1..=3 {
+            let (outcome, _) = ledger
+                .consume("n1", ActionKey::CivicActions, 1, None, None, NOON_UTC)
+                .unwrap();
+            assert!(outcome.allowed);
+            assert_eq!(outcome.count, i);
+        }
+        let (outcome, _) = ledger
+            .consume("n1", ActionKey::CivicActions, 1, None, None, NOON_UTC)
+            .unwrap();
+        assert!(!outcome.allowed);
+        assert_eq!(outcome.remaining, 0);
+        assert_eq!(
+            outcome.reason.as_deref(),
+            Some("Daily limit of 3 reached for civic_actions/day")
+        );
+
+        // Other nullifiers and action keys are independent.
+        assert!(ledger.check("n2", ActionKey::CivicActions, 1, None, NOON_UTC).allowed);
+        assert!(ledger.check("n1", ActionKey::Shares, 1, None, NOON_UTC).allowed);
+    }
+
+    #[test]
+    fn oversized_amounts_are_refused_not_wrapped() {
+        let ledger = ledger();
+        ledger
+            .consume("n1", ActionKey::Posts, 1, None, None, NOON_UTC)
+            .unwrap();
+        let (outcome, _) = ledger
+            .consume("n1", ActionKey::Posts, u32::MAX, None, None, NOON_UTC)
+            .unwrap();
+        assert!(!outcome.allowed);
+        assert_eq!(outcome.count, 1);
+        assert!(!ledger.check("n1", ActionKey::Analyses, u32::MAX, Some("t"), NOON_UTC).allowed);
+    }
+
+    #[test]
+    fn check_does_not_consume() {
+        let ledger = ledger();
+        let outcome = ledger.check("n1", ActionKey::Posts, 5, None, NOON_UTC);
+        assert!(outcome.allowed);
+        assert_eq!(outcome.count, 5);
+        assert_eq!(ledger.check("n1", ActionKey::Posts, 1, None, NOON_UTC).count, 1);
+    }
+
+    #[test]
+    fn analyses_are_capped_per_topic() {
+        let ledger = ledger();
+        for _ in 0..5 {
+            let (outcome, _) = ledger
+                .consume("n1", ActionKey::Analyses, 1, Some("topic-a"), None, NOON_UTC)
+                .unwrap();
+            assert!(outcome.allowed);
+        }
+        let (outcome, _) = ledger
+            .consume("n1", ActionKey::Analyses, 1, Some("topic-a"), None, NOON_UTC)
+            .unwrap();
+        assert!(!outcome.allowed);
+        assert_eq!(outcome.topic_count, Some(5));
+        assert!(outcome.reason.unwrap().starts_with("Per-topic cap of 5"));
+
+        let other = ledger.check("n1", ActionKey::Analyses, 1, Some("topic-b"), NOON_UTC);
+        assert!(other.allowed);
+        assert_eq!(other.count, 6);
+        assert_eq!(other.topic_count, Some(1));
+    }
+
+    #[test]
+    fn rolls_over_at_local_midnight() {
+        let utc = ledger();
+        let la = BudgetLedger::new(JsonFile::memory(), "America/Los_Angeles".parse().unwrap()).unwrap();
+        for ledger in [&utc, &la] {
+            for _ in 0..3 {
+                ledger
+                    .consume("n1", ActionKey::CivicActions, 1, None, None, NOON_UTC)
+                    .unwrap();
+            }
+        }
+
+        // 13 hours later it is a new day in UTC but not yet in Los Angeles.
+        let later = NOON_UTC + 13 * HOUR;
+        assert_eq!(utc.day(later), "2027-01-16");
+        assert_eq!(la.day(later), "2027-01-15");
+        assert!(utc.check("n1", ActionKey::CivicActions, 1, None, later).allowed);
+        assert!(!la.check("n1", ActionKey::CivicActions, 1, None, later).allowed);
+    }
+
+    #[test]
+    fn idempotency_keys_prevent_double_consumption() {
+        let ledger = ledger();
+        let consume = |key: Option<&str>, amount| {
+            ledger.consume("n1", ActionKey::Posts, amount, None, key, NOON_UTC)
+        };
+
+        let (first, replayed) = consume(Some("req-1"), 2).unwrap();
+        assert!(!replayed);
+        let (again, replayed) = consume(Some("req-1"), 2).unwrap();
+        assert!(replayed);
+        assert_eq!(again, first);
+        assert_eq!(ledger.check("n1", ActionKey::Posts, 1, None, NOON_UTC).count, 3);
+
+        assert_eq!(consume(Some("req-1"), 3), Err(BudgetError::IdempotencyConflict));
+        let (_, replayed) = consume(Some("req-2"), 2).unwrap();
+        assert!(!replayed);
+    }
+
+    #[test]
+    fn ledger_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("budget-{:016x}", rand::random::<u64>()));
+        let file = JsonFile::at(dir.join("budgets.json"));
+        let ledger = BudgetLedger::new(file.clone(), Tz::UTC).unwrap();
+        ledger
+            .consume("n1", ActionKey::Shares, 4, None, Some("req-1"), NOON_UTC)
+            .unwrap();
+
+        let reloaded = BudgetLedger::new(file, Tz::UTC).unwrap();
+        assert_eq!(reloaded.check("n1", ActionKey::Shares, 1, None, NOON_UTC).count, 5);
+        let (_, replayed) = reloaded
+            .consume("n1", ActionKey::Shares, 4, None, Some("req-1"), NOON_UTC)
+            .unwrap();
+        assert!(replayed);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn flush_writes_a_snapshot() {
+        let dir = std::env::temp_dir().join(format!("budget-{:016x}", rand::random::<u64>()));
+        let ledger = BudgetLedger::new(JsonFile::at(dir.join("budgets.json")), Tz::UTC).unwrap();
+        assert!(!dir.join("budgets.json").exists());
+        ledger.flush().unwrap();
+        assert!(dir.join("budgets.json").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,428 @@
+//! Fault injection (chaos) layer — DEV/E2E only.
+//!
+//! Lets client developers exercise the timeout and fallback paths in
+//! `useIdentity` without a flaky network.  Faults are configured per route
+//! through `CHAOS_CONFIG` (JSON) and can be forced per request with the
+//! `x-chaos` header.  The layer refuses to activate under `PROD` posture.
+//!
+//! Example `CHAOS_CONFIG`:
+//!
+//! ```json
+//! {
+//!   "allowHeader": true,
+//!   "routes": {
+//!     "verify": {
+//!       "latency": { "kind": "uniform", "minMs": 200, "maxMs": 4000 },
+//!       "errorRate": 0.1,
+//!       "dropRate": 0.05,
+//!       "malformedRate": 0.05
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! Header directives are comma separated: `latency=2500`, `error`,
+//! `error=502`, `drop`, `malformed`.
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::Rng;
+use serde::Deserialize;
+use warp::{Filter, Rejection};
+
+/// Request header used to force a fault on a single request.
+pub const CHAOS_HEADER: &str = "x-chaos";
+
+/// Default status code for injected server errors.
+const DEFAULT_ERROR_STATUS: u16 = 503;
+
+/// Upper bound on any injected delay, so a typo cannot hang a test suite.
+const MAX_LATENCY_MS: u64 = 60_000;
+
+// ── configuration ──────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ChaosConfig {
+    /// Whether the `x-chaos` request header is honored.
+    #[serde(default = "default_true")]
+    pub allow_header: bool,
+    /// Fault profiles keyed by route name (`"verify"`, `"health"`, ...).
+    #[serde(default)]
+    pub routes: HashMap<String, RouteFaults>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RouteFaults {
+    #[serde(default)]
+    pub latency: Option<LatencyDistribution>,
+    /// Probability in `[0, 1]` of answering with a 5xx.
+    #[serde(default)]
+    pub error_rate: f64,
+    /// 5xx status code used for injected errors (defaults to 503).
+    #[serde(default)]
+    pub error_status: Option<u16>,
+    /// Probability in `[0, 1]` of aborting the connection.
+    #[serde(default)]
+    pub drop_rate: f64,
+    /// Probability in `[0, 1]` of answering 200 with a truncated JSON body.
+    #[serde(default)]
+    pub malformed_rate: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum LatencyDistribution {
+    #[serde(rename_all = "camelCase")]
+    Fixed { ms: u64 },
+    #[serde(rename_all = "camelCase")]
+    Uniform { min_ms: u64, max_ms: u64 },
+    #[serde(rename_all = "camelCase")]
+    Exponential { mean_ms: u64 },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl LatencyDistribution {
+    fn sample(&self, rng: &mut impl Rng) -> Duration {
+        let ms = match *self {
+            LatencyDistribution::Fixed { ms } => ms,
+            LatencyDistribution::Uniform { min_ms, max_ms } => {
+                if max_ms <= min_ms {
+                    min_ms
+                } else {
+                    rng.gen_range(min_ms..=max_ms)
+                }
+            }
+            LatencyDistribution::Exponential { mean_ms } => {
+                let u: f64 = rng.gen();
+                (-(mean_ms as f64) * (1.0 - u).ln()) as u64
+            }
+        };
+        Duration::from_millis(ms.min(MAX_LATENCY_MS))
+    }
+}
+
+impl ChaosConfig {
+    /// Load from `CHAOS_CONFIG`, or an empty header-only config when
+    /// `CHAOS_ENABLED=true`.  Returns `None` when chaos is off, when the
+    /// config does not parse, or when `posture` is `PROD`.
+    pub fn from_env(posture: &str) -> Option<Arc<Self>> {
+        let raw = env::var("CHAOS_CONFIG").ok();
+        let enabled = env::var("CHAOS_ENABLED")
+            .map(|v| v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if raw.is_none() && !enabled {
+            return None;
+        }
+        if !is_allowed_in(posture) {
+            tracing::warn!("chaos layer requested but refused under {posture} posture");
+            return None;
+        }
+        let config = match raw {
+            Some(json) => match ChaosConfig::parse(&json) {
+                Ok(config) => config,
+                Err(err) => {
+                    tracing::warn!("ignoring invalid CHAOS_CONFIG: {err}");
+                    return None;
+                }
+            },
+            None => ChaosConfig {
+                allow_header: true,
+                routes: HashMap::new(),
+            },
+        };
+        Some(Arc::new(config))
+    }
+
+    /// Parse and validate a `CHAOS_CONFIG` document: rates must lie in
+    /// `[0, 1]` and error statuses must be 5xx.
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let config: ChaosConfig = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        for (route, faults) in &config.routes {
+            for (field, rate) in [
+                ("errorRate", faults.error_rate),
+                ("dropRate", faults.drop_rate),
+                ("malformedRate", faults.malformed_rate),
+            ] {
+                if !(0.0..=1.0).contains(&rate) {
+                    return Err(format!("routes.{route}.{field}: {rate} is not in [0, 1]"));
+                }
+            }
+            if let Some(status) = faults.error_status.filter(|s| !(500..=599).contains(s)) {
+                return Err(format!("routes.{route}.errorStatus: {status} is not a 5xx status"));
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Chaos is never available under `PROD` posture.
+pub fn is_allowed_in(posture: &str) -> bool {
+    !posture.eq_ignore_ascii_case("PROD")
+}
+
+// ── fault planning ─────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultKind {
+    Error(u16),
+    Drop,
+    Malformed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultPlan {
+    pub delay: Option<Duration>,
+    pub fault: Option<FaultKind>,
+}
+
+/// Decide which faults to inject for one request.  Header directives take
+/// precedence over the route profile; anything not named by the header is
+/// still drawn from the profile.
+pub fn plan(
+    config: &ChaosConfig,
+    route: &str,
+    header: Option<&str>,
+    rng: &mut impl Rng,
+) -> FaultPlan {
+    let mut plan = FaultPlan::default();
+
+    if let Some(profile) = config.routes.get(route) {
+        plan.delay = profile.latency.as_ref().map(|d| d.sample(rng));
+        let roll: f64 = rng.gen();
+        let error = profile.error_rate.clamp(0.0, 1.0);
+        let drop = profile.drop_rate.clamp(0.0, 1.0);
+        let malformed = profile.malformed_rate.clamp(0.0, 1.0);
+        plan.fault = if roll < error {
+            Some(FaultKind::Error(
+                profile.error_status.unwrap_or(DEFAULT_ERROR_STATUS),
+            ))
+        } else if roll < error + drop {
+            Some(FaultKind::Drop)
+        } else if roll < error + drop + malformed {
+            Some(FaultKind::Malformed)
+        } else {
+            None
+        };
+    }
+
+    if config.allow_header {
+        if let Some(header) = header {
+            apply_header(&mut plan, header);
+        }
+    }
+    plan
+}
+
+fn apply_header(plan: &mut FaultPlan, header: &str) {
+    for directive in header.split(',').map(str::trim) {
+        let (name, value) = match directive.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (directive, None),
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "latency" => {
+                if let Some(ms) = value.and_then(|v| v.parse::<u64>().ok()) {
+                    plan.delay = Some(Duration::from_millis(ms.min(MAX_LATENCY_MS)));
+                }
+            }
+            "error" => {
+                let status = value
+                    .and_then(|v| v.parse::<u16>().ok())
+                    .filter(|s| (500..=599).contains(s))
+                    .unwrap_or(DEFAULT_ERROR_STATUS);
+                plan.fault = Some(FaultKind::Error(status));
+            }
+            "drop" => plan.fault = Some(FaultKind::Drop),
+            "malformed" => plan.fault = Some(FaultKind::Malformed),
+            "none" => plan.fault = None,
+            _ => {}
+        }
+    }
+}
+
+// ── warp integration ───────────────────────────────────────────────────
+
+/// Rejection carrying an injected fault; rendered by `handle_rejection`.
+#[derive(Debug)]
+pub struct ChaosFault(pub FaultKind);
+
+impl warp::reject::Reject for ChaosFault {}
+
+/// Filter that sleeps and/or rejects with [`ChaosFault`] according to the
+/// plan for `route`.  A no-op when `config` is `None`.
+pub fn inject(
+    config: Option<Arc<ChaosConfig>>,
+    route: &'static str,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>(CHAOS_HEADER)
+        .and_then(move |header: Option<String>| {
+            let config = config.clone();
+            async move {
+                let Some(config) = config else {
+                    return Ok(());
+                };
+                let plan = plan(&config, route, header.as_deref(), &mut rand::thread_rng());
+                if let Some(delay) = plan.delay {
+                    tokio::time::sleep(delay).await;
+                }
+                match plan.fault {
+                    Some(kind) => Err(warp::reject::custom(ChaosFault(kind))),
+                    None => Ok(()),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// Response whose body stream fails immediately, so hyper aborts the
+/// connection instead of completing the exchange.
+pub fn dropped_connection() -> warp::reply::Response {
+    let stream = futures_util::stream::once(async {
+        Err::<Vec<u8>, std::io::Error>(std::io::Error::new(
+            std::io::ErrorKind::ConnectionAborted,
+            "chaos: connection dropped",
+        ))
+    });
+    warp::reply::Response::new(warp::hyper::Body::wrap_stream(stream))
+}
+
+/// Response that claims to be JSON but is cut off mid-object.
+pub fn malformed_body() -> warp::reply::Response {
+    let mut res = warp::reply::Response::new(r#"{"token":"session-"#.into());
+    res.headers_mut().insert(
+        warp::http::header::CONTENT_TYPE,
+        warp::http::HeaderValue::from_static("application/json"),
+    );
+    res
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn config_with(route: &str, faults: RouteFaults) -> ChaosConfig {
+        ChaosConfig {
+            allow_header: true,
+            routes: HashMap::from([(route.to_string(), faults)]),
+        }
+    }
+
+    #[test]
+    fn refused_under_prod_posture() {
+        assert!(!is_allowed_in("PROD"));
+        assert!(!is_allowed_in("prod"));
+        assert!(is_allowed_in("DEV"));
+        assert!(is_allowed_in("STAGING"));
+    }
+
+    #[test]
+    fn parses_route_profiles() {
+        let config: ChaosConfig = serde_json::from_str(
+            r#"{"routes":{"verify":{"latency":{"kind":"uniform","minMs":10,"maxMs":20},"errorRate":0.5}}}"#,
+        )
+        .unwrap();
+        assert!(config.allow_header);
+        let verify = &config.routes["verify"];
+        assert_eq!(
+            verify.latency,
+            Some(LatencyDistribution::Uniform { min_ms: 10, max_ms: 20 })
+        );
+        assert!((verify.error_rate - 0.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn rejects_unknown_config_fields() {
+        assert!(serde_json::from_str::<ChaosConfig>(r#"{"routs":{}}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_non_5xx_statuses_and_out_of_range_rates() {
+        assert!(ChaosConfig::parse(r#"{"routes":{"verify":{"errorStatus":502}}}"#).is_ok());
+        for json in [
+            r#"{"routes":{"verify":{"errorStatus":200}}}"#,
+            r#"{"routes":{"verify":{"errorStatus":404}}}"#,
+            r#"{"routes":{"verify":{"errorRate":1.5}}}"#,
+            r#"{"routes":{"verify":{"dropRate":-0.1}}}"#,
+            r#"{"routes":{"health":{"malformedRate":2}}}"#,
+        ] {
+            assert!(ChaosConfig::parse(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn latency_samples_stay_in_range() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let dist = LatencyDistribution::Uniform { min_ms: 100, max_ms: 200 };
+        for _ in 0..100 {
+            let d = dist.sample(&mut rng).as_millis();
+            assert!((100..=200).contains(&d));
+        }
+        let capped = LatencyDistribution::Fixed { ms: u64::MAX };
+        assert_eq!(capped.sample(&mut rng), Duration::from_millis(MAX_LATENCY_MS));
+    }
+
+    #[test]
+    fn certain_rates_always_fire() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let config = config_with(
+            "verify",
+            RouteFaults {
+                drop_rate: 1.0,
+                ..RouteFaults::default()
+            },
+        );
+        for _ in 0..20 {
+            assert_eq!(
+                plan(&config, "verify", None, &mut rng).fault,
+                Some(FaultKind::Drop)
+            );
+            assert_eq!(plan(&config, "health", None, &mut rng), FaultPlan::default());
+        }
+    }
+
+    #[test]
+    fn header_overrides_profile() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let config = config_with(
+            "verify",
+            RouteFaults {
+                error_rate: 1.0,
+                ..RouteFaults::default()
+            },
+        );
+        let p = plan(&config, "verify", Some("latency=2500, malformed"), &mut rng);
+        assert_eq!(p.delay, Some(Duration::from_millis(2500)));
+        assert_eq!(p.fault, Some(FaultKind::Malformed));
+
+        let p = plan(&config, "verify", Some("none"), &mut rng);
+        assert_eq!(p.fault, None);
+
+        let p = plan(&config, "health", Some("error=502"), &mut rng);
+        assert_eq!(p.fault, Some(FaultKind::Error(502)));
+
+        let p = plan(&config, "health", Some("error=200"), &mut rng);
+        assert_eq!(p.fault, Some(FaultKind::Error(DEFAULT_ERROR_STATUS)));
+    }
+
+    #[test]
+    fn header_ignored_when_disallowed() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let config = ChaosConfig {
+            allow_header: false,
+            routes: HashMap::new(),
+        };
+        assert_eq!(plan(&config, "verify", Some("drop"), &mut rng), FaultPlan::default());
+    }
+}
@@ -0,0 +1,340 @@
+//! Command-line subcommands.  Without arguments the binary runs the server.
+//!
+//! ```text
+//! attestation-verifier verify PAYLOAD.json [--mock]
+//! attestation-verifier nullifier derive DEVICE_KEY
+//! attestation-verifier token inspect TOKEN
+//! attestation-verifier keys generate
+//! attestation-verifier audit verify [FILE]
+//! attestation-verifier audit export [FILE] [--from SEQ] [--to SEQ]
+//! ```
+//!
+//! Subcommands read the same environment as the server (`NULLIFIER_SALT`,
+//! `TRUST_POLICY_PATH`, `VERIFIER_SIGNING_KEY`, ...) so their answers match
+//! a running verifier, without the network.  `verify` skips rate limits
+//! and proof of work, which depend on live traffic.  Audit `FILE` defaults
+//! to `$VERIFIER_STATE_DIR/audit.ndjson`.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Write};
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::keys::KeyRing;
+use crate::policy::PolicyStore;
+use crate::session::{self, Revocations, SessionClaims};
+use crate::verdict::Verdict;
+use crate::{
+    audit, collect_issues, current_timestamp, derive_nullifier, is_mock_enabled,
+    nullifier_key_info, run_backend, AttestationPayload, NullifierKeyInfo, Platform,
+    ValidationIssue,
+};
+
+const USAGE: &str = "usage:
+  attestation-verifier verify PAYLOAD.json [--mock]
+  attestation-verifier nullifier derive DEVICE_KEY
+  attestation-verifier token inspect TOKEN
+  attestation-verifier keys generate
+  attestation-verifier audit verify [FILE]
+  attestation-verifier audit export [FILE] [--from SEQ] [--to SEQ]";
+
+/// Run a subcommand; returns the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match dispatch(&args, &mut io::stdout().lock()) {
+        Ok(()) => 0,
+        Err(message) => {
+            eprintln!("error: {message}");
+            1
+        }
+    }
+}
+
+fn dispatch(args: &[&str], out: &mut dyn Write) -> Result<(), String> {
+    match args {
+        ["verify", rest @ ..] => verify(rest, out),
+        ["nullifier", "derive", device_key] => nullifier_derive(device_key, out),
+        ["token", "inspect", token] => token_inspect(token, out),
+        ["keys", "generate"] => keys_generate(out),
+        ["audit", "verify", rest @ ..] => audit_verify(rest, out),
+        ["audit", "export", rest @ ..] => audit_export(rest, out),
+        ["help" | "--help" | "-h"] => print(out, USAGE),
+        _ => Err(format!("unknown command\n{USAGE}")),
+    }
+}
+
+fn print(out: &mut dyn Write, text: &str) -> Result<(), String> {
+    writeln!(out, "{text}").map_err(|e| e.to_string())
+}
+
+fn print_json(out: &mut dyn Write, value: &impl Serialize) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(value).expect("reports serialize");
+    print(out, &text)
+}
+
+// ── verify ─────────────────────────────────────────────────────────────
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyReport {
+    platform: Platform,
+    mock: bool,
+    nullifier: String,
+    nullifier_key: NullifierKeyInfo,
+    scaled_trust_score: u32,
+    verdict: Verdict,
+}
+
+#[derive(Serialize)]
+struct InvalidPayload {
+    issues: Vec<ValidationIssue>,
+}
+
+/// Validate and score a payload exactly as `/verify` would.
+fn verify(args: &[&str], out: &mut dyn Write) -> Result<(), String> {
+    let (path, mock_flag) = match args {
+        [path] => (path, false),
+        [path, "--mock"] | ["--mock", path] => (path, true),
+        _ => return Err(USAGE.to_string()),
+    };
+    let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let payload: AttestationPayload =
+        serde_json::from_str(&text).map_err(|e| format!("{path}: {e}"))?;
+    let issues = collect_issues(&payload);
+    if !issues.is_empty() {
+        print_json(out, &InvalidPayload { issues })?;
+        return Err("payload failed validation".to_string());
+    }
+    let policy = PolicyStore::from_env()?;
+    let mock = is_mock_enabled(&mock_flag.then(|| "true".to_string()));
+    let codes = run_backend(&payload, mock);
+    let verdict = policy.current().evaluate(payload.platform, &codes);
+    print_json(
+        out,
+        &VerifyReport {
+            platform: payload.platform,
+            mock,
+            nullifier: derive_nullifier(&payload.device_key),
+            nullifier_key: nullifier_key_info(),
+            scaled_trust_score: session::scale(verdict.trust_score),
+            verdict,
+        },
+    )
+}
+
+// ── nullifier / token / keys ───────────────────────────────────────────
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NullifierReport {
+    nullifier: String,
+    key: NullifierKeyInfo,
+}
+
+fn nullifier_derive(device_key: &str, out: &mut dyn Write) -> Result<(), String> {
+    print_json(
+        out,
+        &NullifierReport {
+            nullifier: derive_nullifier(device_key),
+            key: nullifier_key_info(),
+        },
+    )
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenReport {
+    header: session::Header,
+    claims: SessionClaims,
+    scaled_trust_score: u32,
+    /// `valid`, a token error code, or `unchecked` without a configured key.
+    status: &'static str,
+}
+
+/// Decode a session token and check it against `VERIFIER_SIGNING_KEY` and
+/// the revocation list, when configured.
+fn token_inspect(token: &str, out: &mut dyn Write) -> Result<(), String> {
+    let malformed = |e: session::TokenError| format!("cannot decode token: {}", e.code());
+    let header = session::decode_header(token).map_err(malformed)?;
+    let claims = session::decode_unverified(token).map_err(malformed)?;
+    let (keys, ephemeral) = KeyRing::from_env()?;
+    let status = if ephemeral {
+        "unchecked"
+    } else {
+        match session::verify(&keys, token, current_timestamp()) {
+            Ok(claims) if Revocations::from_env()?.is_revoked(&claims) => {
+                session::TokenError::Revoked.code()
+            }
+            Ok(_) => "valid",
+            Err(e) => e.code(),
+        }
+    };
+    print_json(
+        out,
+        &TokenReport {
+            header,
+            scaled_trust_score: claims.scaled_score(),
+            claims,
+            status,
+        },
+    )
+}
+
+/// A fresh signing key in env-file form.
+fn keys_generate(out: &mut dyn Write) -> Result<(), String> {
+    let seed: [u8; 32] = rand::random();
+    let ring = KeyRing::from_seed(seed);
+    print(
+        out,
+        &format!(
+            "# kid {}, public key {}\nVERIFIER_SIGNING_KEY={}\nVERIFIER_SIGNING_KEY_CREATED_AT={}",
+            ring.kid(),
+            hex::encode(ring.verifying_key().as_bytes()),
+            hex::encode(seed),
+            ring.created_at()
+        ),
+    )
+}
+
+// ── audit ──────────────────────────────────────────────────────────────
+
+fn audit_verify(args: &[&str], out: &mut dyn Write) -> Result<(), String> {
+    let path = match args {
+        [] => default_audit_path()?,
+        [file] => PathBuf::from(file),
+        _ => return Err(USAGE.to_string()),
+    };
+    let summary = audit::verify_chain(open(&path)?).map_err(|e| e.to_string())?;
+    print(
+        out,
+        &format!("ok: {} entries, head {}", summary.entries, summary.head),
+    )
+}
+
+fn audit_export(args: &[&str], out: &mut dyn Write) -> Result<(), String> {
+    let mut path = None;
+    let mut from = 0;
+    let mut to = u64::MAX;
+    let mut args = args.iter();
+    while let Some(&arg) = args.next() {
+        match arg {
+            "--from" => from = seq_arg("--from", args.next())?,
+            "--to" => to = seq_arg("--to", args.next())?,
+            file if path.is_none() && !file.starts_with("--") => path = Some(PathBuf::from(file)),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let path = match path {
+        Some(path) => path,
+        None => default_audit_path()?,
+    };
+    audit::export(open(&path)?, from, to, out)?;
+    Ok(())
+}
+
+fn seq_arg(flag: &str, value: Option<&&str>) -> Result<u64, String> {
+    value
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| format!("{flag} needs a sequence number"))
+}
+
+fn default_audit_path() -> Result<PathBuf, String> {
+    audit::default_path().ok_or_else(|| "no FILE given and VERIFIER_STATE_DIR is not set".to_string())
+}
+
+fn open(path: &PathBuf) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::verdict::AssuranceLevel;
+
+    fn run_ok(args: &[&str]) -> String {
+        let mut out = Vec::new();
+        dispatch(args, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{name}", rand::random::<u64>()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn verify_scores_a_payload_file() {
+        let path = temp_file(
+            "payload.json",
+            r#"{"platform":"web","integrityToken":"long-enough-token","deviceKey":"dGVzdC1kZXZpY2Uta2V5","nonce":"a1b2c3d4e5f60718"}"#,
+        );
+        let report: serde_json::Value =
+            serde_json::from_str(&run_ok(&["verify", path.to_str().unwrap()])).unwrap();
+        assert_eq!(report["nullifier"], derive_nullifier("dGVzdC1kZXZpY2Uta2V5"));
+        assert_eq!(report["scaledTrustScore"], 8000);
+        assert_eq!(report["verdict"]["signals"][0]["code"], "WEB_TOKEN_PRESENT");
+
+        let invalid = temp_file(
+            "invalid.json",
+            r#"{"platform":"web","integrityToken":"","deviceKey":"short","nonce":"n"}"#,
+        );
+        let mut out = Vec::new();
+        assert!(dispatch(&["verify", invalid.to_str().unwrap()], &mut out).is_err());
+        assert!(String::from_utf8(out).unwrap().contains("\"issues\""));
+        fs::remove_file(path).unwrap();
+        fs::remove_file(invalid).unwrap();
+    }
+
+    #[test]
+    fn nullifier_derive_matches_the_server() {
+        let report: serde_json::Value =
+            serde_json::from_str(&run_ok(&["nullifier", "derive", "device-1"])).unwrap();
+        assert_eq!(report["nullifier"], derive_nullifier("device-1"));
+        assert_eq!(report["key"]["version"], nullifier_key_info().version);
+    }
+
+    #[test]
+    fn token_inspect_decodes_claims() {
+        let claims = SessionClaims {
+            jti: "jti-1".to_string(),
+            sub: "nullifier-abc".to_string(),
+            score: 0.8,
+            assurance: AssuranceLevel::Silver,
+            iat: 1_700_000_000,
+            exp: 1_700_000_100,
+        };
+        let keys = KeyRing::from_seed([4; 32]);
+        let token = session::issue(&keys, &claims);
+        let report: serde_json::Value =
+            serde_json::from_str(&run_ok(&["token", "inspect", &token])).unwrap();
+        assert_eq!(report["header"]["kid"], keys.kid());
+        assert_eq!(report["claims"]["sub"], "nullifier-abc");
+        assert_eq!(report["scaledTrustScore"], 8000);
+        assert!(dispatch(&["token", "inspect", "not-a-token"], &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn generated_keys_load() {
+        let output = run_ok(&["keys", "generate"]);
+        let seed = output
+            .lines()
+            .find_map(|line| line.strip_prefix("VERIFIER_SIGNING_KEY="))
+            .unwrap();
+        let ring = KeyRing::from_seed(hex::decode(seed).unwrap().try_into().unwrap());
+        assert!(output.starts_with(&format!("# kid {}", ring.kid())));
+    }
+
+    #[test]
+    fn unknown_commands_are_refused() {
+        assert!(dispatch(&["nullifier"], &mut Vec::new()).is_err());
+        assert!(dispatch(&["keys", "generate", "extra"], &mut Vec::new()).is_err());
+        assert!(run_ok(&["help"]).starts_with("usage:"));
+    }
+}
@@ -0,0 +1,266 @@
+//! Constituency proof verification (identity spec §4.2).
+//!
+//! Server-side twin of `verifyConstituencyProof` in
+//! `packages/types/src/constituency-verification.ts`: same checks, same
+//! order, same error strings.  Two differences: the expected nullifier
+//! comes from the caller's verified session token, never a client-supplied
+//! string, and freshness is checked against the residency root registry
+//! instead of accepting any non-empty root.  The server also requires a
+//! Merkle inclusion proof binding `(district_hash, nullifier)` to that
+//! root; a path that does not reach it is a `malformed_proof`, so the
+//! error set stays the TS one.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::merkle::InclusionProof;
+use crate::residency::RootRegistry;
+
+/// `{ district_hash, nullifier, merkle_root }` (spec §4.1).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ConstituencyProof {
+    pub district_hash: String,
+    pub nullifier: String,
+    pub merkle_root: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofVerificationError {
+    NullifierMismatch,
+    DistrictMismatch,
+    StaleProof,
+    MalformedProof,
+}
+
+impl ConstituencyProof {
+    /// Extract a proof from untyped JSON.  `None` when the value is not an
+    /// object, or any field is missing, null or not a string — the cases the
+    /// TS verifier reports as `malformed_proof`.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let field = |name: &str| value.get(name)?.as_str().map(str::to_string);
+        Some(Self {
+            district_hash: field("district_hash")?,
+            nullifier: field("nullifier")?,
+            merkle_root: field("merkle_root")?,
+        })
+    }
+}
+
+/// Check `proof` and its residency-set `inclusion` path for the session
+/// holder `expected_nullifier` against the target jurisdiction
+/// `expected_district_hash`; `now` is unix seconds.
+pub fn verify_proof(
+    proof: Option<&Value>,
+    inclusion: Option<&Value>,
+    expected_nullifier: &str,
+    expected_district_hash: &str,
+    roots: &RootRegistry,
+    now: u64,
+) -> Result<ConstituencyProof, ProofVerificationError> {
+    // 1. Malformed check
+    let proof = proof
+        .and_then(ConstituencyProof::from_value)
+        .filter(|p| !p.district_hash.is_empty() && !p.nullifier.is_empty())
+        .ok_or(ProofVerificationError::MalformedProof)?;
+    let inclusion: InclusionProof = inclusion
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .ok_or(ProofVerificationError::MalformedProof)?;
+
+    // 2. Nullifier match
+    if proof.nullifier != expected_nullifier {
+        return Err(ProofVerificationError::NullifierMismatch);
+    }
+
+    // 3. District match
+    if proof.district_hash != expected_district_hash {
+        return Err(ProofVerificationError::DistrictMismatch);
+    }
+
+    // 4. Freshness — a registered root inside the window
+    if !roots.is_fresh(&proof.merkle_root, now) {
+        return Err(ProofVerificationError::StaleProof);
+    }
+
+    // 5. Inclusion — the claim is a leaf of that residency set
+    let hasher = roots.tree_hash().hasher();
+    match inclusion.verify(hasher, &proof.district_hash, &proof.nullifier, &proof.merkle_root) {
+        Ok(true) => Ok(proof),
+        Ok(false) | Err(_) => Err(ProofVerificationError::MalformedProof),
+    }
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyRing;
+    use crate::merkle::{MerkleTree, TreeHash};
+    use crate::residency::{RootAnnouncement, DEFAULT_MAX_ROOT_AGE_SECS};
+    use serde_json::json;
+
+    const NULLIFIER: &str = "nullifier-abc";
+    const DISTRICT: &str = "district-1";
+    const NOW: u64 = 1_800_000_000;
+
+    /// A published four-resident tree with the caller at index 2.
+    struct Fixture {
+        roots: RootRegistry,
+        root: String,
+        inclusion: Value,
+    }
+
+    fn fixture() -> Fixture {
+        let hasher = TreeHash::Sha256.hasher();
+        let leaves = [
+            ("district-1", "nullifier-x"),
+            ("district-2", "nullifier-y"),
+            (DISTRICT, NULLIFIER),
+            ("district-1", "nullifier-z"),
+        ]
+        .iter()
+        .map(|(d, n)| hasher.leaf(d, n))
+        .collect();
+        let tree = MerkleTree::build(hasher, leaves);
+        let inclusion = tree.proof(2);
+
+        let authority = KeyRing::from_seed([3; 32]);
+        let roots = RootRegistry::new(vec![authority.verifying_key()], DEFAULT_MAX_ROOT_AGE_SECS);
+        roots
+            .publish(RootAnnouncement::sign(&authority, &tree.root_hex(), NOW), NOW)
+            .unwrap();
+        Fixture {
+            roots,
+            root: tree.root_hex(),
+            inclusion: json!({ "index": inclusion.index, "path": inclusion.path }),
+        }
+    }
+
+    impl Fixture {
+        fn proof(&self) -> Value {
+            json!({
+                "district_hash": DISTRICT,
+                "nullifier": NULLIFIER,
+                "merkle_root": self.root
+            })
+        }
+
+        fn with(&self, field: &str, value: Value) -> Value {
+            let mut p = self.proof();
+            p[field] = value;
+            p
+        }
+
+        fn verify(&self, proof: Option<&Value>) -> Result<ConstituencyProof, ProofVerificationError> {
+            self.verify_at(proof, Some(&self.inclusion), NOW)
+        }
+
+        fn verify_at(
+            &self,
+            proof: Option<&Value>,
+            inclusion: Option<&Value>,
+            now: u64,
+        ) -> Result<ConstituencyProof, ProofVerificationError> {
+            verify_proof(proof, inclusion, NULLIFIER, DISTRICT, &self.roots, now)
+        }
+    }
+
+    #[test]
+    fn accepts_matching_fresh_included_proof() {
+        let f = fixture();
+        let verified = f.verify(Some(&f.proof())).unwrap();
+        assert_eq!(verified.merkle_root, f.root);
+    }
+
+    #[test]
+    fn reports_mismatches() {
+        let f = fixture();
+        assert_eq!(
+            f.verify(Some(&f.with("nullifier", json!("other")))),
+            Err(ProofVerificationError::NullifierMismatch)
+        );
+        assert_eq!(
+            f.verify(Some(&f.with("district_hash", json!("other")))),
+            Err(ProofVerificationError::DistrictMismatch)
+        );
+    }
+
+    #[test]
+    fn unknown_blank_or_expired_roots_are_stale() {
+        let f = fixture();
+        for root in ["", "   \n\t", "root-unknown", "mock-root"] {
+            assert_eq!(
+                f.verify(Some(&f.with("merkle_root", json!(root)))),
+                Err(ProofVerificationError::StaleProof)
+            );
+        }
+        let day = 24 * 60 * 60;
+        let inclusion = Some(&f.inclusion);
+        assert!(f.verify_at(Some(&f.proof()), inclusion, NOW + 30 * day).is_ok());
+        assert_eq!(
+            f.verify_at(Some(&f.proof()), inclusion, NOW + 31 * day),
+            Err(ProofVerificationError::StaleProof)
+        );
+    }
+
+    #[test]
+    fn wrong_path_is_malformed() {
+        let f = fixture();
+        let mut other_slot = f.inclusion.clone();
+        other_slot["index"] = json!(3);
+        assert_eq!(
+            f.verify_at(Some(&f.proof()), Some(&other_slot), NOW),
+            Err(ProofVerificationError::MalformedProof)
+        );
+    }
+
+    #[test]
+    fn malformed_cases_match_ts_verifier() {
+        let f = fixture();
+        let mut missing = f.proof();
+        missing.as_object_mut().unwrap().remove("merkle_root");
+        let cases = [
+            None,
+            Some(Value::Null),
+            Some(json!("not an object")),
+            Some(missing),
+            Some(f.with("merkle_root", Value::Null)),
+            Some(f.with("district_hash", json!(""))),
+            Some(f.with("nullifier", json!(""))),
+            Some(f.with("nullifier", json!(42))),
+        ];
+        for case in &cases {
+            assert_eq!(
+                f.verify(case.as_ref()),
+                Err(ProofVerificationError::MalformedProof),
+                "{case:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn missing_or_unusable_inclusion_is_malformed() {
+        let f = fixture();
+        let mut bad_hex = f.inclusion.clone();
+        bad_hex["path"][0] = json!("not-hex");
+        let mut out_of_range = f.inclusion.clone();
+        out_of_range["index"] = json!(4);
+        for inclusion in [None, Some(json!({ "index": 2 })), Some(bad_hex), Some(out_of_range)] {
+            assert_eq!(
+                f.verify_at(Some(&f.proof()), inclusion.as_ref(), NOW),
+                Err(ProofVerificationError::MalformedProof),
+                "{inclusion:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn errors_serialize_like_ts() {
+        let json = serde_json::to_string(&ProofVerificationError::StaleProof).unwrap();
+        assert_eq!(json, "\"stale_proof\"");
+        let json = serde_json::to_string(&ProofVerificationError::MalformedProof).unwrap();
+        assert_eq!(json, "\"malformed_proof\"");
+    }
+}
@@ -0,0 +1,640 @@
+//! Delegation grants for agentic familiars (identity spec §6).
+//!
+//! Server-side twin of `packages/types/src/delegation-utils.ts`, with real
+//! signatures.  The verifier signs each `DelegationGrant` for a principal's
+//! familiar; the familiar then signs `OnBehalfOfAssertion`s with the key
+//! bound into its grant.  An action is allowed only when the grant is
+//! authentic, live, not revoked (nor any ancestor), covers the scope, and —
+//! for Tier 3 scopes — the principal presents a freshly issued session at
+//! action time.  Sub-delegation must strictly attenuate the parent's scopes.
+//!
+//! Timestamps are unix milliseconds, as in the TS types.  Grants live in
+//! memory; a restart drops them and familiars re-request delegation.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::RwLock;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::keys::KeyRing;
+use crate::session::SessionClaims;
+
+/// Default grant lifetime: one hour ("minutes/hours default", §6.1).
+pub const DEFAULT_GRANT_TTL_MS: u64 = 60 * 60 * 1000;
+
+/// Longest grant the verifier will sign (`DEFAULT_MAX_GRANT_LIFETIME_MS`).
+pub const MAX_GRANT_TTL_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// An assertion must be made this close to the action.
+pub const ASSERTION_MAX_AGE_MS: u64 = 60 * 1000;
+
+/// Tier 3 actions need a principal session issued this recently.
+pub const PRINCIPAL_PROOF_MAX_AGE_SECS: u64 = 5 * 60;
+
+const GRANT_CONTEXT: &str = "vh-delegation-grant/v1";
+const ASSERTION_CONTEXT: &str = "vh-on-behalf-of/v1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Draft,
+    Triage,
+    Analyze,
+    Post,
+    Comment,
+    Share,
+    Moderate,
+    Vote,
+    Fund,
+    CivicAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Tier {
+    /// Tier 1.
+    Suggest,
+    /// Tier 2.
+    Act,
+    /// Tier 3: needs human approval at action time.
+    HighImpact,
+}
+
+impl Tier {
+    /// `TIER_SCOPES` presets.
+    pub fn scopes(self) -> &'static [Scope] {
+        match self {
+            Tier::Suggest => &[Scope::Draft, Scope::Triage],
+            Tier::Act => &[Scope::Analyze, Scope::Post, Scope::Comment, Scope::Share],
+            Tier::HighImpact => &[Scope::Moderate, Scope::Vote, Scope::Fund, Scope::CivicAction],
+        }
+    }
+}
+
+impl Scope {
+    pub fn tier(self) -> Tier {
+        match self {
+            Scope::Draft | Scope::Triage => Tier::Suggest,
+            Scope::Analyze | Scope::Post | Scope::Comment | Scope::Share => Tier::Act,
+            Scope::Moderate | Scope::Vote | Scope::Fund | Scope::CivicAction => Tier::HighImpact,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Scope::Draft => "draft",
+            Scope::Triage => "triage",
+            Scope::Analyze => "analyze",
+            Scope::Post => "post",
+            Scope::Comment => "comment",
+            Scope::Share => "share",
+            Scope::Moderate => "moderate",
+            Scope::Vote => "vote",
+            Scope::Fund => "fund",
+            Scope::CivicAction => "civic_action",
+        }
+    }
+}
+
+/// `DelegationGrant` from `@vh/types`, plus the familiar's public key and
+/// the parent grant for sub-delegations.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DelegationGrant {
+    pub grant_id: String,
+    pub principal_nullifier: String,
+    pub familiar_id: String,
+    /// Hex Ed25519 key that signs this familiar's assertions.
+    pub familiar_key: String,
+    pub scopes: Vec<Scope>,
+    pub issued_at: u64,
+    pub expires_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_grant_id: Option<String>,
+    /// `<kid>.<base64url Ed25519>` by the verifier.
+    pub signature: String,
+}
+
+impl DelegationGrant {
+    fn signing_input(&self) -> String {
+        let scopes: Vec<&str> = self.scopes.iter().map(|s| s.as_str()).collect();
+        format!(
+            "{GRANT_CONTEXT}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            self.grant_id,
+            self.principal_nullifier,
+            self.familiar_id,
+            self.familiar_key,
+            scopes.join(","),
+            self.issued_at,
+            self.expires_at,
+            self.parent_grant_id.as_deref().unwrap_or(""),
+        )
+    }
+}
+
+/// `OnBehalfOfAssertion` from `@vh/types`, signed by the familiar key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct OnBehalfOfAssertion {
+    pub principal_nullifier: String,
+    pub familiar_id: String,
+    pub grant_id: String,
+    pub issued_at: u64,
+    /// Base64url Ed25519 signature by the grant's `familiarKey`.
+    pub signature: String,
+}
+
+impl OnBehalfOfAssertion {
+    fn signing_input(&self) -> String {
+        format!(
+            "{ASSERTION_CONTEXT}\n{}\n{}\n{}\n{}",
+            self.principal_nullifier, self.familiar_id, self.grant_id, self.issued_at
+        )
+    }
+
+    /// Sign an assertion for `grant` with the familiar's key.
+    #[cfg(test)]
+    pub fn sign(familiar: &KeyRing, grant: &DelegationGrant, issued_at: u64) -> Self {
+        let mut assertion = Self {
+            principal_nullifier: grant.principal_nullifier.clone(),
+            familiar_id: grant.familiar_id.clone(),
+            grant_id: grant.grant_id.clone(),
+            issued_at,
+            signature: String::new(),
+        };
+        let signature = familiar.sign(assertion.signing_input().as_bytes());
+        assertion.signature = URL_SAFE_NO_PAD.encode(signature.to_bytes());
+        assertion
+    }
+}
+
+/// Why a delegation request or action was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denial {
+    UnknownGrant,
+    BadGrantSignature,
+    Revoked,
+    NotActive,
+    Expired,
+    ScopeNotGranted,
+    NotAttenuated,
+    PrincipalMismatch,
+    AssertionMismatch,
+    BadAssertionSignature,
+    StaleAssertion,
+    ApprovalRequired,
+    StaleApproval,
+}
+
+impl Denial {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Denial::UnknownGrant => "GRANT_UNKNOWN",
+            Denial::BadGrantSignature => "GRANT_SIGNATURE_INVALID",
+            Denial::Revoked => "GRANT_REVOKED",
+            Denial::NotActive => "GRANT_NOT_ACTIVE",
+            Denial::Expired => "GRANT_EXPIRED",
+            Denial::ScopeNotGranted => "SCOPE_NOT_GRANTED",
+            Denial::NotAttenuated => "SCOPE_NOT_ATTENUATED",
+            Denial::PrincipalMismatch => "PRINCIPAL_MISMATCH",
+            Denial::AssertionMismatch => "ASSERTION_MISMATCH",
+            Denial::BadAssertionSignature => "ASSERTION_SIGNATURE_INVALID",
+            Denial::StaleAssertion => "ASSERTION_STALE",
+            Denial::ApprovalRequired => "PRINCIPAL_PROOF_REQUIRED",
+            Denial::StaleApproval => "PRINCIPAL_PROOF_STALE",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Denial::UnknownGrant => "grant is not known to this verifier",
+            Denial::BadGrantSignature => "grant signature does not verify",
+            Denial::Revoked => "grant or one of its parents is revoked",
+            Denial::NotActive => "grant is not active yet",
+            Denial::Expired => "grant is expired",
+            Denial::ScopeNotGranted => "scope is not granted",
+            Denial::NotAttenuated => "sub-delegation must grant a strict subset of the parent's scopes",
+            Denial::PrincipalMismatch => "grant belongs to a different principal",
+            Denial::AssertionMismatch => "assertion does not match grant",
+            Denial::BadAssertionSignature => "assertion is not signed by the familiar key",
+            Denial::StaleAssertion => "assertion is not bound to the action time",
+            Denial::ApprovalRequired => "high-impact scope requires a fresh principal proof",
+            Denial::StaleApproval => "principal proof is too old for a high-impact action",
+        }
+    }
+}
+
+/// A grant to sign; `principal_nullifier` comes from a verified session or
+/// the parent grant, never from the client.
+pub struct GrantRequest {
+    pub principal_nullifier: String,
+    pub familiar_id: String,
+    pub familiar_key: VerifyingKey,
+    pub scopes: Vec<Scope>,
+    pub ttl_ms: u64,
+    pub parent: Option<DelegationGrant>,
+}
+
+struct Entry {
+    grant: DelegationGrant,
+    revoked_at: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct DelegationRegistry {
+    grants: RwLock<HashMap<String, Entry>>,
+}
+
+impl DelegationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sign and record a grant.  Sub-delegations are clamped to the
+    /// parent's expiry and must strictly attenuate its scopes.
+    pub fn issue(
+        &self,
+        keys: &KeyRing,
+        request: GrantRequest,
+        now: u64,
+    ) -> Result<DelegationGrant, Denial> {
+        let scopes: BTreeSet<Scope> = request.scopes.into_iter().collect();
+        let mut expires_at = now + request.ttl_ms.min(MAX_GRANT_TTL_MS);
+        if let Some(parent) = &request.parent {
+            if parent.principal_nullifier != request.principal_nullifier {
+                return Err(Denial::PrincipalMismatch);
+            }
+            let parent_scopes: BTreeSet<Scope> = parent.scopes.iter().copied().collect();
+            if !scopes.is_subset(&parent_scopes) || scopes.len() == parent_scopes.len() {
+                return Err(Denial::NotAttenuated);
+            }
+            expires_at = expires_at.min(parent.expires_at);
+        }
+
+        let mut grant = DelegationGrant {
+            grant_id: format!("{:032x}", rand::random::<u128>()),
+            principal_nullifier: request.principal_nullifier,
+            familiar_id: request.familiar_id,
+            familiar_key: hex::encode(request.familiar_key.as_bytes()),
+            scopes: scopes.into_iter().collect(),
+            issued_at: now,
+            expires_at,
+            parent_grant_id: request.parent.map(|p| p.grant_id),
+            signature: String::new(),
+        };
+        let signature = keys.sign(grant.signing_input().as_bytes());
+        grant.signature = format!("{}.{}", keys.kid(), URL_SAFE_NO_PAD.encode(signature.to_bytes()));
+
+        let mut grants = self.grants.write().unwrap_or_else(|e| e.into_inner());
+        // Expired grants can never verify again; drop them as we go.
+        grants.retain(|_, e| e.grant.expires_at > now);
+        grants.insert(
+            grant.grant_id.clone(),
+            Entry {
+                grant: grant.clone(),
+                revoked_at: None,
+            },
+        );
+        Ok(grant)
+    }
+
+    /// A recorded, authentic, live grant whose ancestors are not revoked.
+    pub fn check_grant(&self, keys: &KeyRing, grant_id: &str, now: u64) -> Result<DelegationGrant, Denial> {
+        let grants = self.grants.read().unwrap_or_else(|e| e.into_inner());
+        let entry = grants.get(grant_id).ok_or(Denial::UnknownGrant)?;
+        let grant = &entry.grant;
+        let (kid, sig) = grant.signature.split_once('.').ok_or(Denial::BadGrantSignature)?;
+        let sig: [u8; 64] = URL_SAFE_NO_PAD
+            .decode(sig)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or(Denial::BadGrantSignature)?;
+        if !keys.verify(kid, grant.signing_input().as_bytes(), &Signature::from_bytes(&sig)) {
+            return Err(Denial::BadGrantSignature);
+        }
+
+        let mut cursor = Some(entry);
+        while let Some(e) = cursor {
+            if e.revoked_at.is_some_and(|at| now >= at) {
+                return Err(Denial::Revoked);
+            }
+            cursor = e.grant.parent_grant_id.as_ref().and_then(|id| grants.get(id));
+        }
+        if now < grant.issued_at {
+            return Err(Denial::NotActive);
+        }
+        if now >= grant.expires_at {
+            return Err(Denial::Expired);
+        }
+        Ok(grant.clone())
+    }
+
+    /// Check an assertion against its grant: fields match, the familiar
+    /// key signed it, and it was made within `ASSERTION_MAX_AGE_MS` of now.
+    pub fn authenticate(
+        &self,
+        keys: &KeyRing,
+        assertion: &OnBehalfOfAssertion,
+        now: u64,
+    ) -> Result<DelegationGrant, Denial> {
+        let grant = self.check_grant(keys, &assertion.grant_id, now)?;
+        if assertion.principal_nullifier != grant.principal_nullifier
+            || assertion.familiar_id != grant.familiar_id
+        {
+            return Err(Denial::AssertionMismatch);
+        }
+        let familiar_key = hex::decode(&grant.familiar_key)
+            .ok()
+            .and_then(|b| <[u8; 32]>::try_from(b).ok())
+            .and_then(|b| VerifyingKey::from_bytes(&b).ok())
+            .ok_or(Denial::BadGrantSignature)?;
+        let sig: [u8; 64] = URL_SAFE_NO_PAD
+            .decode(&assertion.signature)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or(Denial::BadAssertionSignature)?;
+        familiar_key
+            .verify(assertion.signing_input().as_bytes(), &Signature::from_bytes(&sig))
+            .map_err(|_| Denial::BadAssertionSignature)?;
+        if assertion.issued_at > now + ASSERTION_MAX_AGE_MS || now > assertion.issued_at + ASSERTION_MAX_AGE_MS {
+            return Err(Denial::StaleAssertion);
+        }
+        Ok(grant)
+    }
+
+    /// May the familiar behind `assertion` act with `scope` now?  Tier 3
+    /// scopes also need `principal_proof`: a session for the same principal
+    /// issued within `PRINCIPAL_PROOF_MAX_AGE_SECS`.
+    pub fn authorize(
+        &self,
+        keys: &KeyRing,
+        assertion: &OnBehalfOfAssertion,
+        scope: Scope,
+        principal_proof: Option<&SessionClaims>,
+        now: u64,
+    ) -> Result<DelegationGrant, Denial> {
+        let grant = self.authenticate(keys, assertion, now)?;
+        if !grant.scopes.contains(&scope) {
+            return Err(Denial::ScopeNotGranted);
+        }
+        if scope.tier() == Tier::HighImpact {
+            let proof = principal_proof.ok_or(Denial::ApprovalRequired)?;
+            if proof.sub != grant.principal_nullifier {
+                return Err(Denial::PrincipalMismatch);
+            }
+            if now / 1000 > proof.iat + PRINCIPAL_PROOF_MAX_AGE_SECS {
+                return Err(Denial::StaleApproval);
+            }
+        }
+        Ok(grant)
+    }
+
+    /// Revoke a grant on behalf of its principal; descendants fall with it.
+    /// Returns the number of grants (including this one) now revoked.
+    pub fn revoke(&self, grant_id: &str, principal: &str, now: u64) -> Result<usize, Denial> {
+        let mut grants = self.grants.write().unwrap_or_else(|e| e.into_inner());
+        let entry = grants.get_mut(grant_id).ok_or(Denial::UnknownGrant)?;
+        if entry.grant.principal_nullifier != principal {
+            return Err(Denial::PrincipalMismatch);
+        }
+        match entry.revoked_at {
+            Some(at) if at <= now => {}
+            _ => entry.revoked_at = Some(now),
+        }
+
+        let mut affected = vec![grant_id.to_string()];
+        let mut i = 0;
+        while i < affected.len() {
+            let parent = affected[i].clone();
+            affected.extend(
+                grants
+                    .values()
+                    .filter(|e| e.grant.parent_grant_id.as_deref() == Some(parent.as_str()))
+                    .map(|e| e.grant.grant_id.clone()),
+            );
+            i += 1;
+        }
+        Ok(affected.len())
+    }
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verdict::AssuranceLevel;
+
+    const NOW: u64 = 1_800_000_000_000;
+    const PRINCIPAL: &str = "nullifier-principal";
+
+    struct Fixture {
+        keys: KeyRing,
+        registry: DelegationRegistry,
+        familiar: KeyRing,
+    }
+
+    fn fixture() -> Fixture {
+        Fixture {
+            keys: KeyRing::from_seed([1; 32]),
+            registry: DelegationRegistry::new(),
+            familiar: KeyRing::from_seed([2; 32]),
+        }
+    }
+
+    impl Fixture {
+        fn request(&self, scopes: &[Scope], parent: Option<DelegationGrant>) -> GrantRequest {
+            GrantRequest {
+                principal_nullifier: PRINCIPAL.to_string(),
+                familiar_id: "familiar-1".to_string(),
+                familiar_key: self.familiar.verifying_key(),
+                scopes: scopes.to_vec(),
+                ttl_ms: DEFAULT_GRANT_TTL_MS,
+                parent,
+            }
+        }
+
+        fn grant(&self, scopes: &[Scope]) -> DelegationGrant {
+            self.registry.issue(&self.keys, self.request(scopes, None), NOW).unwrap()
+        }
+
+        fn act(&self, grant: &DelegationGrant, scope: Scope, at: u64) -> Result<DelegationGrant, Denial> {
+            let assertion = OnBehalfOfAssertion::sign(&self.familiar, grant, at);
+            self.registry.authorize(&self.keys, &assertion, scope, None, at)
+        }
+    }
+
+    fn session(sub: &str, iat: u64) -> SessionClaims {
+        SessionClaims {
+            jti: "j".to_string(),
+            sub: sub.to_string(),
+            score: 0.9,
+            assurance: AssuranceLevel::Silver,
+            iat,
+            exp: iat + 3600,
+        }
+    }
+
+    #[test]
+    fn tier_presets_partition_scopes() {
+        for tier in [Tier::Suggest, Tier::Act, Tier::HighImpact] {
+            for scope in tier.scopes() {
+                assert_eq!(scope.tier(), tier);
+            }
+        }
+        let total: usize = [Tier::Suggest, Tier::Act, Tier::HighImpact]
+            .iter()
+            .map(|t| t.scopes().len())
+            .sum();
+        assert_eq!(total, 10);
+    }
+
+    #[test]
+    fn issued_grant_authorizes_its_scopes_until_expiry() {
+        let f = fixture();
+        let grant = f.grant(Tier::Act.scopes());
+        assert_eq!(grant.expires_at, NOW + DEFAULT_GRANT_TTL_MS);
+        assert!(grant.signature.starts_with(f.keys.kid()));
+
+        assert!(f.act(&grant, Scope::Post, NOW + 1000).is_ok());
+        assert_eq!(f.act(&grant, Scope::Draft, NOW), Err(Denial::ScopeNotGranted));
+        assert_eq!(f.act(&grant, Scope::Post, grant.expires_at), Err(Denial::Expired));
+    }
+
+    #[test]
+    fn ttl_is_capped() {
+        let f = fixture();
+        let mut request = f.request(&[Scope::Draft], None);
+        request.ttl_ms = 7 * MAX_GRANT_TTL_MS;
+        let grant = f.registry.issue(&f.keys, request, NOW).unwrap();
+        assert_eq!(grant.expires_at, NOW + MAX_GRANT_TTL_MS);
+    }
+
+    #[test]
+    fn assertions_must_be_signed_and_timely() {
+        let f = fixture();
+        let grant = f.grant(&[Scope::Comment]);
+
+        let imposter = KeyRing::from_seed([9; 32]);
+        let forged = OnBehalfOfAssertion::sign(&imposter, &grant, NOW);
+        assert_eq!(
+            f.registry.authorize(&f.keys, &forged, Scope::Comment, None, NOW),
+            Err(Denial::BadAssertionSignature)
+        );
+
+        let mut other_principal = OnBehalfOfAssertion::sign(&f.familiar, &grant, NOW);
+        other_principal.principal_nullifier = "nullifier-other".to_string();
+        assert_eq!(
+            f.registry.authorize(&f.keys, &other_principal, Scope::Comment, None, NOW),
+            Err(Denial::AssertionMismatch)
+        );
+
+        let old = OnBehalfOfAssertion::sign(&f.familiar, &grant, NOW);
+        assert_eq!(
+            f.registry
+                .authorize(&f.keys, &old, Scope::Comment, None, NOW + ASSERTION_MAX_AGE_MS + 1),
+            Err(Denial::StaleAssertion)
+        );
+    }
+
+    #[test]
+    fn grants_from_another_verifier_do_not_verify() {
+        let f = fixture();
+        let grant = f.grant(&[Scope::Comment]);
+        let other_keys = KeyRing::from_seed([7; 32]);
+        let assertion = OnBehalfOfAssertion::sign(&f.familiar, &grant, NOW);
+        assert_eq!(
+            f.registry.authorize(&other_keys, &assertion, Scope::Comment, None, NOW),
+            Err(Denial::BadGrantSignature)
+        );
+    }
+
+    #[test]
+    fn high_impact_needs_fresh_principal_proof() {
+        let f = fixture();
+        let grant = f.grant(&[Scope::Vote]);
+        let assertion = OnBehalfOfAssertion::sign(&f.familiar, &grant, NOW);
+        let authorize = |proof: Option<&SessionClaims>| {
+            f.registry.authorize(&f.keys, &assertion, Scope::Vote, proof, NOW)
+        };
+
+        assert_eq!(authorize(None), Err(Denial::ApprovalRequired));
+        let now_secs = NOW / 1000;
+        assert!(authorize(Some(&session(PRINCIPAL, now_secs - 10))).is_ok());
+        assert_eq!(
+            authorize(Some(&session(PRINCIPAL, now_secs - PRINCIPAL_PROOF_MAX_AGE_SECS - 1))),
+            Err(Denial::StaleApproval)
+        );
+        assert_eq!(
+            authorize(Some(&session("nullifier-other", now_secs))),
+            Err(Denial::PrincipalMismatch)
+        );
+    }
+
+    #[test]
+    fn sub_delegation_must_attenuate() {
+        let f = fixture();
+        let parent = f.grant(&[Scope::Post, Scope::Comment]);
+
+        let child = f
+            .registry
+            .issue(&f.keys, f.request(&[Scope::Comment], Some(parent.clone())), NOW)
+            .unwrap();
+        assert_eq!(child.parent_grant_id.as_deref(), Some(parent.grant_id.as_str()));
+        assert!(child.expires_at <= parent.expires_at);
+
+        for scopes in [&[Scope::Post, Scope::Comment][..], &[Scope::Vote], &[Scope::Comment, Scope::Share]] {
+            assert_eq!(
+                f.registry
+                    .issue(&f.keys, f.request(scopes, Some(parent.clone())), NOW)
+                    .map(|_| ()),
+                Err(Denial::NotAttenuated),
+                "{scopes:?}"
+            );
+        }
+
+        let mut foreign = f.request(&[Scope::Post], Some(parent));
+        foreign.principal_nullifier = "nullifier-other".to_string();
+        assert_eq!(
+            f.registry.issue(&f.keys, foreign, NOW).map(|_| ()),
+            Err(Denial::PrincipalMismatch)
+        );
+    }
+
+    #[test]
+    fn child_expiry_is_clamped_to_parent() {
+        let f = fixture();
+        let mut short = f.request(&[Scope::Post, Scope::Comment], None);
+        short.ttl_ms = 1000;
+        let parent = f.registry.issue(&f.keys, short, NOW).unwrap();
+        let child = f
+            .registry
+            .issue(&f.keys, f.request(&[Scope::Post], Some(parent.clone())), NOW)
+            .unwrap();
+        assert_eq!(child.expires_at, parent.expires_at);
+    }
+
+    #[test]
+    fn revocation_cascades_to_sub_grants() {
+        let f = fixture();
+        let parent = f.grant(&[Scope::Post, Scope::Comment]);
+        let child = f
+            .registry
+            .issue(&f.keys, f.request(&[Scope::Comment], Some(parent.clone())), NOW)
+            .unwrap();
+
+        assert_eq!(
+            f.registry.revoke(&parent.grant_id, "nullifier-other", NOW),
+            Err(Denial::PrincipalMismatch)
+        );
+        assert_eq!(f.registry.revoke(&parent.grant_id, PRINCIPAL, NOW + 10), Ok(2));
+        assert!(f.act(&parent, Scope::Post, NOW + 5).is_ok());
+        assert_eq!(f.act(&parent, Scope::Post, NOW + 10), Err(Denial::Revoked));
+        assert_eq!(f.act(&child, Scope::Comment, NOW + 10), Err(Denial::Revoked));
+        assert_eq!(f.registry.revoke("nope", PRINCIPAL, NOW), Err(Denial::UnknownGrant));
+    }
+}
@@ -0,0 +1,297 @@
+//! Canonical `district_hash` derivation (identity spec §4).
+//!
+//! A region code (`US-CA-12`) is private; its public form is
+//! `district_hash = HMAC-SHA256(key, "vh-district/v1" ‖ 0 ‖ context ‖ 0 ‖ code)`
+//! over the normalized code, hex encoded.  The context (e.g. `season-0`,
+//! `us-house-2026`) separates hash spaces so a hash from one programme
+//! cannot be replayed in another.  Clients, rep directories and proof
+//! issuers all take hashes from here instead of computing their own.
+//!
+//! The key comes from `DISTRICT_HASH_KEY` (hex, 32 bytes).  Without it a
+//! fixed development key is used, which makes hashes guessable — DEV only.
+
+use std::env;
+use std::fs;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use utoipa::ToSchema;
+
+const DOMAIN: &[u8] = b"vh-district/v1";
+
+/// Public development key; hashes derived with it offer no privacy.
+const DEV_KEY: [u8; 32] = *b"vh-dev-district-hash-key-0000000";
+
+const MAX_CONTEXT_LEN: usize = 64;
+const MAX_DISTRICT_LEN: usize = 8;
+
+/// US House seats per state after the 2020 apportionment.  Single-seat
+/// states have one at-large district, coded `AL`.
+const US_HOUSE_SEATS: &[(&str, u32)] = &[
+    ("AL", 7), ("AK", 1), ("AZ", 9), ("AR", 4), ("CA", 52), ("CO", 8), ("CT", 5),
+    ("DE", 1), ("FL", 28), ("GA", 14), ("HI", 2), ("ID", 2), ("IL", 17), ("IN", 9),
+    ("IA", 4), ("KS", 4), ("KY", 6), ("LA", 6), ("ME", 2), ("MD", 8), ("MA", 9),
+    ("MI", 13), ("MN", 8), ("MS", 4), ("MO", 8), ("MT", 2), ("NE", 3), ("NV", 4),
+    ("NH", 2), ("NJ", 12), ("NM", 3), ("NY", 26), ("NC", 14), ("ND", 1), ("OH", 15),
+    ("OK", 5), ("OR", 6), ("PA", 17), ("RI", 2), ("SC", 7), ("SD", 1), ("TN", 9),
+    ("TX", 38), ("UT", 4), ("VT", 1), ("VA", 11), ("WA", 10), ("WV", 2), ("WI", 8),
+    ("WY", 1),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionCodeError {
+    Empty,
+    BadCountry,
+    BadSubdivision,
+    BadDistrict,
+    TooManySegments,
+}
+
+impl RegionCodeError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            RegionCodeError::Empty => "region code is empty",
+            RegionCodeError::BadCountry => "country must be an ISO 3166-1 alpha-2 code",
+            RegionCodeError::BadSubdivision => "subdivision must be 1-3 letters or digits",
+            RegionCodeError::BadDistrict => "district must be 1-8 letters or digits",
+            RegionCodeError::TooManySegments => {
+                "region code has at most country, subdivision and district"
+            }
+        }
+    }
+}
+
+/// Normalize a region code to `CC[-SUB[-DISTRICT]]`: upper case, `-`
+/// separators (`_`, `.`, `/` and spaces are accepted), numeric districts
+/// without leading zeros and district `0`/`00` as at-large `AL`.
+pub fn normalize_region(code: &str) -> Result<String, RegionCodeError> {
+    let upper = code.trim().to_ascii_uppercase();
+    if upper.is_empty() {
+        return Err(RegionCodeError::Empty);
+    }
+    let segments: Vec<&str> = upper
+        .split(|c: char| matches!(c, '-' | '_' | '.' | '/') || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let alnum = |s: &str| s.chars().all(|c| c.is_ascii_alphanumeric());
+
+    let mut normalized = String::new();
+    match segments.first() {
+        Some(country) if country.len() == 2 && country.chars().all(|c| c.is_ascii_uppercase()) => {
+            normalized.push_str(country);
+        }
+        _ => return Err(RegionCodeError::BadCountry),
+    }
+    if let Some(sub) = segments.get(1) {
+        if sub.len() > 3 || !alnum(sub) {
+            return Err(RegionCodeError::BadSubdivision);
+        }
+        normalized.push('-');
+        normalized.push_str(sub);
+    }
+    if let Some(district) = segments.get(2) {
+        if district.len() > MAX_DISTRICT_LEN || !alnum(district) {
+            return Err(RegionCodeError::BadDistrict);
+        }
+        normalized.push('-');
+        if district.chars().all(|c| c.is_ascii_digit()) {
+            match district.trim_start_matches('0') {
+                "" => normalized.push_str("AL"),
+                digits => normalized.push_str(digits),
+            }
+        } else {
+            normalized.push_str(district);
+        }
+    }
+    if segments.len() > 3 {
+        return Err(RegionCodeError::TooManySegments);
+    }
+    Ok(normalized)
+}
+
+/// Contexts are short lowercase labels: `[a-z0-9][a-z0-9._:-]*`.
+pub fn is_valid_context(context: &str) -> bool {
+    let mut chars = context.chars();
+    context.len() <= MAX_CONTEXT_LEN
+        && chars
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && chars.all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '_' | ':' | '-')
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DistrictEntry {
+    pub region_code: String,
+    pub district_hash: String,
+}
+
+pub struct DistrictHasher {
+    key: [u8; 32],
+    /// Normalized region codes published by `/district/hashes`.
+    regions: Vec<String>,
+}
+
+impl DistrictHasher {
+    pub fn new(key: [u8; 32], regions: Vec<String>) -> Self {
+        Self { key, regions }
+    }
+
+    /// The development key with the built-in US region list.
+    pub fn dev() -> Self {
+        Self::new(DEV_KEY, us_regions())
+    }
+
+    /// Load `DISTRICT_HASH_KEY` and, if set, `DISTRICT_REGIONS_PATH` (one
+    /// region code per line, `#` comments).  Returns whether the
+    /// development key is in use alongside the hasher.
+    pub fn from_env() -> Result<(Self, bool), String> {
+        let (key, dev_key) = match env::var("DISTRICT_HASH_KEY") {
+            Ok(hex_key) => {
+                let key = hex::decode(hex_key.trim())
+                    .ok()
+                    .and_then(|b| b.try_into().ok())
+                    .ok_or("DISTRICT_HASH_KEY must be 32 bytes of hex")?;
+                (key, false)
+            }
+            Err(_) => (DEV_KEY, true),
+        };
+        let regions = match env::var("DISTRICT_REGIONS_PATH") {
+            Ok(path) => {
+                let text = fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+                parse_region_list(&text).map_err(|e| format!("{path}: {e}"))?
+            }
+            Err(_) => us_regions(),
+        };
+        Ok((Self::new(key, regions), dev_key))
+    }
+
+    /// `district_hash` for an already-normalized code.
+    pub fn hash(&self, context: &str, normalized_code: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes any key length");
+        mac.update(DOMAIN);
+        mac.update(&[0]);
+        mac.update(context.as_bytes());
+        mac.update(&[0]);
+        mac.update(normalized_code.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Every published region with its hash under `context`.
+    pub fn publish(&self, context: &str) -> Vec<DistrictEntry> {
+        self.regions
+            .iter()
+            .map(|code| DistrictEntry {
+                region_code: code.clone(),
+                district_hash: self.hash(context, code),
+            })
+            .collect()
+    }
+}
+
+fn parse_region_list(text: &str) -> Result<Vec<String>, String> {
+    let mut regions = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let code = normalize_region(line)
+            .map_err(|e| format!("line {}: `{line}`: {}", n + 1, e.message()))?;
+        if !regions.contains(&code) {
+            regions.push(code);
+        }
+    }
+    Ok(regions)
+}
+
+/// Each state, then each of its House districts.
+fn us_regions() -> Vec<String> {
+    let mut regions = Vec::new();
+    for (state, seats) in US_HOUSE_SEATS {
+        regions.push(format!("US-{state}"));
+        if *seats == 1 {
+            regions.push(format!("US-{state}-AL"));
+        } else {
+            regions.extend((1..=*seats).map(|d| format!("US-{state}-{d}")));
+        }
+    }
+    regions
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_spellings_to_one_code() {
+        for input in ["US-CA-12", "us-ca-12", " US_CA_012 ", "us.ca.12", "US CA 12"] {
+            assert_eq!(normalize_region(input).unwrap(), "US-CA-12", "{input}");
+        }
+        assert_eq!(normalize_region("us-wy-00").unwrap(), "US-WY-AL");
+        assert_eq!(normalize_region("us-wy-al").unwrap(), "US-WY-AL");
+        assert_eq!(normalize_region("gb").unwrap(), "GB");
+        assert_eq!(normalize_region("GB-ENG").unwrap(), "GB-ENG");
+    }
+
+    #[test]
+    fn rejects_invalid_codes() {
+        assert_eq!(normalize_region("  "), Err(RegionCodeError::Empty));
+        assert_eq!(normalize_region("USA-CA"), Err(RegionCodeError::BadCountry));
+        assert_eq!(normalize_region("1A-CA"), Err(RegionCodeError::BadCountry));
+        assert_eq!(normalize_region("US-CALI"), Err(RegionCodeError::BadSubdivision));
+        assert_eq!(normalize_region("US-CA-1!"), Err(RegionCodeError::BadDistrict));
+        assert_eq!(normalize_region("US-CA-123456789"), Err(RegionCodeError::BadDistrict));
+        assert_eq!(normalize_region("US-CA-12-3"), Err(RegionCodeError::TooManySegments));
+    }
+
+    #[test]
+    fn hashes_are_keyed_and_context_separated() {
+        let hasher = DistrictHasher::dev();
+        let a = hasher.hash("season-0", "US-CA-12");
+        assert_eq!(a, hasher.hash("season-0", "US-CA-12"));
+        assert_eq!(a.len(), 64);
+        assert_ne!(a, hasher.hash("season-1", "US-CA-12"));
+        assert_ne!(a, hasher.hash("season-0", "US-CA-13"));
+        let other_key = DistrictHasher::new([9; 32], Vec::new());
+        assert_ne!(a, other_key.hash("season-0", "US-CA-12"));
+        // The separator keeps context and code from sliding into each other.
+        assert_ne!(hasher.hash("a", "BC"), hasher.hash("ab", "C"));
+    }
+
+    #[test]
+    fn contexts() {
+        assert!(is_valid_context("season-0"));
+        assert!(is_valid_context("us-house:2026"));
+        assert!(!is_valid_context(""));
+        assert!(!is_valid_context("Season-0"));
+        assert!(!is_valid_context("-leading"));
+        assert!(!is_valid_context(&"a".repeat(MAX_CONTEXT_LEN + 1)));
+    }
+
+    #[test]
+    fn builtin_list_covers_the_house() {
+        let regions = us_regions();
+        let districts = regions.iter().filter(|r| r.matches('-').count() == 2).count();
+        assert_eq!(districts, 435);
+        assert_eq!(regions.len(), 435 + 50);
+        for code in &regions {
+            assert_eq!(&normalize_region(code).unwrap(), code);
+        }
+        let published = DistrictHasher::dev().publish("season-0");
+        let ca12 = published.iter().find(|e| e.region_code == "US-CA-12").unwrap();
+        assert_eq!(ca12.district_hash, DistrictHasher::dev().hash("season-0", "US-CA-12"));
+    }
+
+    #[test]
+    fn region_list_files() {
+        let regions = parse_region_list("# districts\nus-ca-12\nUS_CA_012 # dup\n\nus-wy-0\n").unwrap();
+        assert_eq!(regions, ["US-CA-12", "US-WY-AL"]);
+        assert!(parse_region_list("US-CALI").unwrap_err().starts_with("line 1"));
+    }
+}
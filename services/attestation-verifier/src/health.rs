@@ -0,0 +1,257 @@
+//! Liveness, readiness and graceful shutdown.
+//!
+//! `/livez` only says the process is serving requests.  `/readyz` checks
+//! each dependency a verification needs — the state store, the signing key,
+//! the residency trust anchors and, when `BRIDGE_RPC_URL` is set, the bridge
+//! RPC endpoint — and fails while any of them is failing or while the
+//! service is draining for shutdown.
+//!
+//! On SIGTERM (or Ctrl-C) the service flips to draining and keeps serving
+//! for `SHUTDOWN_READINESS_DELAY_SECS` (default 5), so load balancers see
+//! `/readyz` fail and stop routing to it.  Only then does it stop accepting
+//! connections and wait up to `SHUTDOWN_GRACE_SECS` (default 25) for
+//! in-flight requests before flushing state and exiting.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+use warp::http::Uri;
+
+use crate::keys::KeyRing;
+use crate::residency::RootRegistry;
+use crate::store::JsonFile;
+
+/// Default time allowed for in-flight requests once the listener closes.
+pub const DEFAULT_SHUTDOWN_GRACE_SECS: u64 = 25;
+
+/// Default time `/readyz` reports draining before the listener closes.
+pub const DEFAULT_SHUTDOWN_READINESS_DELAY_SECS: u64 = 5;
+
+/// How long a bridge RPC connect may take before it counts as failing.
+const BRIDGE_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    /// Serving, but some requests will be refused (e.g. stale roots).
+    Degraded,
+    Failing,
+    /// Not configured in this deployment; never blocks readiness.
+    Unconfigured,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Set once shutdown begins; readiness fails from then on.
+#[derive(Debug, Default)]
+pub struct Lifecycle {
+    draining: AtomicBool,
+}
+
+impl Lifecycle {
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+}
+
+/// Ready unless draining or a dependency is failing.
+pub fn is_ready(draining: bool, checks: &BTreeMap<&'static str, Check>) -> bool {
+    !draining && checks.values().all(|c| c.status != CheckStatus::Failing)
+}
+
+pub fn check_store(file: &JsonFile) -> Check {
+    let Some(path) = file.path() else {
+        return Check::new(CheckStatus::Unconfigured, "memory-only");
+    };
+    match file.check_writable() {
+        Ok(()) => Check::new(CheckStatus::Ok, format!("{} writable", path.display())),
+        Err(e) => Check::new(CheckStatus::Failing, e),
+    }
+}
+
+/// Sign and verify a probe message with the session key.
+pub fn check_keys(keys: &KeyRing) -> Check {
+    let probe = b"readyz";
+    if keys.verify(keys.kid(), probe, &keys.sign(probe)) {
+        Check::new(CheckStatus::Ok, format!("kid {}", keys.kid()))
+    } else {
+        Check::new(CheckStatus::Failing, "signing key failed its self-check")
+    }
+}
+
+/// Stale or missing roots only degrade the service: constituency proofs are
+/// refused, but a fresh root can still be published to this instance.
+pub fn check_trust_anchors(roots: &RootRegistry, now: u64) -> Check {
+    let authorities = roots.authority_count();
+    if authorities == 0 {
+        return Check::new(CheckStatus::Unconfigured, "no residency authorities");
+    }
+    let fresh = roots.snapshot(now).iter().filter(|r| r.fresh).count();
+    let detail = format!("{authorities} authorities, {fresh} fresh roots");
+    if fresh == 0 {
+        Check::new(CheckStatus::Degraded, detail)
+    } else {
+        Check::new(CheckStatus::Ok, detail)
+    }
+}
+
+/// Can the bridge RPC host be reached?  A TCP connect, not an RPC call.
+pub async fn check_bridge(url: Option<&str>) -> Check {
+    let Some(url) = url else {
+        return Check::new(CheckStatus::Unconfigured, "BRIDGE_RPC_URL not set");
+    };
+    let target = match bridge_target(url) {
+        Ok(target) => target,
+        Err(e) => return Check::new(CheckStatus::Failing, e),
+    };
+    match tokio::time::timeout(BRIDGE_CONNECT_TIMEOUT, tokio::net::TcpStream::connect(&target))
+        .await
+    {
+        Ok(Ok(_)) => Check::new(CheckStatus::Ok, format!("{target} reachable")),
+        Ok(Err(e)) => Check::new(CheckStatus::Failing, format!("{target}: {e}")),
+        Err(_) => Check::new(CheckStatus::Failing, format!("{target}: connect timed out")),
+    }
+}
+
+/// `host:port` of an RPC URL, defaulting the port from the scheme.
+fn bridge_target(url: &str) -> Result<String, String> {
+    let uri: Uri = url
+        .trim()
+        .parse()
+        .map_err(|e| format!("BRIDGE_RPC_URL: {e}"))?;
+    let host = uri
+        .host()
+        .ok_or_else(|| "BRIDGE_RPC_URL has no host".to_string())?;
+    let port = match (uri.port_u16(), uri.scheme_str()) {
+        (Some(port), _) => port,
+        (None, Some("https" | "wss")) => 443,
+        (None, Some("http" | "ws")) => 80,
+        _ => return Err("BRIDGE_RPC_URL needs an http(s) or ws(s) scheme".to_string()),
+    };
+    Ok(format!("{host}:{port}"))
+}
+
+/// `SHUTDOWN_GRACE_SECS`, or the default.
+pub fn grace_from_env() -> Result<Duration, String> {
+    secs_from_env("SHUTDOWN_GRACE_SECS", DEFAULT_SHUTDOWN_GRACE_SECS)
+}
+
+/// `SHUTDOWN_READINESS_DELAY_SECS`, or the default.
+pub fn readiness_delay_from_env() -> Result<Duration, String> {
+    secs_from_env(
+        "SHUTDOWN_READINESS_DELAY_SECS",
+        DEFAULT_SHUTDOWN_READINESS_DELAY_SECS,
+    )
+}
+
+fn secs_from_env(var: &str, default: u64) -> Result<Duration, String> {
+    match env::var(var) {
+        Ok(secs) => secs
+            .trim()
+            .parse()
+            .map(Duration::from_secs)
+            .map_err(|_| format!("{var} must be whole seconds")),
+        Err(_) => Ok(Duration::from_secs(default)),
+    }
+}
+
+/// Resolves on SIGTERM or Ctrl-C.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("cannot listen for Ctrl-C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("cannot listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => tracing::info!("Ctrl-C received — shutting down"),
+        () = terminate => tracing::info!("SIGTERM received — shutting down"),
+    }
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bridge_target_defaults_port_from_scheme() {
+        assert_eq!(bridge_target("https://rpc.test/v1").unwrap(), "rpc.test:443");
+        assert_eq!(bridge_target("http://10.0.0.5:8545").unwrap(), "10.0.0.5:8545");
+        assert_eq!(bridge_target("ws://node").unwrap(), "node:80");
+        assert!(bridge_target("rpc.test").is_err());
+        assert!(bridge_target("ftp://rpc.test").is_err());
+    }
+
+    #[tokio::test]
+    async fn bridge_check_reports_unreachable_host() {
+        assert_eq!(check_bridge(None).await.status, CheckStatus::Unconfigured);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        assert_eq!(check_bridge(Some(&url)).await.status, CheckStatus::Ok);
+        drop(listener);
+        assert_eq!(check_bridge(Some(&url)).await.status, CheckStatus::Failing);
+    }
+
+    #[test]
+    fn store_check_needs_a_writable_dir() {
+        assert_eq!(
+            check_store(&JsonFile::memory()).status,
+            CheckStatus::Unconfigured
+        );
+        let blocked = std::env::temp_dir().join(format!("readyz-{:016x}", rand::random::<u64>()));
+        std::fs::write(&blocked, b"a file, not a directory").unwrap();
+        let check = check_store(&JsonFile::at(blocked.join("budgets.json")));
+        assert_eq!(check.status, CheckStatus::Failing);
+        std::fs::remove_file(&blocked).unwrap();
+    }
+
+    #[test]
+    fn readiness_fails_on_failing_checks_or_draining() {
+        let mut checks = BTreeMap::new();
+        checks.insert("store", Check::new(CheckStatus::Unconfigured, ""));
+        checks.insert("trustAnchors", Check::new(CheckStatus::Degraded, ""));
+        assert!(is_ready(false, &checks));
+        assert!(!is_ready(true, &checks));
+        checks.insert("bridgeRpc", Check::new(CheckStatus::Failing, ""));
+        assert!(!is_ready(false, &checks));
+    }
+}
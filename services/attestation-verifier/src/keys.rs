@@ -0,0 +1,241 @@
+//! Verifier signing key.
+//!
+//! Session tokens are signed with Ed25519 so relays and services can check
+//! them without sharing a secret.  The 32-byte seed comes from
+//! `VERIFIER_SIGNING_KEY` (hex); without it an ephemeral key is generated at
+//! startup, which invalidates every issued token on restart (fine for DEV).
+//! `VERIFIER_SIGNING_KEY_CREATED_AT` (unix seconds) records when a configured
+//! key was minted so its age can be monitored; otherwise age counts from load.
+//!
+//! The admin API can rotate at runtime to a seed the operator supplies
+//! (minted with `keys generate`), so the same seed can be set as
+//! `VERIFIER_SIGNING_KEY` for the next restart.  Up to `MAX_RETIRED_KEYS`
+//! previous keys keep verifying (so outstanding sessions and grants survive)
+//! unless the rotation retires them outright.
+
+use std::env;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+/// Previous keys that still verify after a rotation.
+pub const MAX_RETIRED_KEYS: usize = 4;
+
+/// An Ed25519 key plus its key id (`kid`), and any retired keys that still
+/// verify.
+pub struct KeyRing {
+    signing: SigningKey,
+    kid: String,
+    /// Unix seconds.
+    created_at: u64,
+    /// Newest first.
+    retired: Vec<(String, VerifyingKey)>,
+}
+
+impl KeyRing {
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let signing = SigningKey::from_bytes(&seed);
+        let kid = fingerprint(&signing.verifying_key());
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            signing,
+            kid,
+            created_at,
+            retired: Vec::new(),
+        }
+    }
+
+    pub fn generate() -> Self {
+        Self::from_seed(rand::random())
+    }
+
+    /// A ring for a hex seed, as `keys generate` prints it.  `created_at`
+    /// defaults to now.
+    pub fn from_hex(hex_seed: &str, created_at: Option<u64>) -> Result<Self, String> {
+        let mut ring = Self::from_seed(parse_seed(hex_seed)?);
+        if let Some(created_at) = created_at {
+            ring.created_at = created_at;
+        }
+        Ok(ring)
+    }
+
+    /// Load from `VERIFIER_SIGNING_KEY`, or generate an ephemeral key.
+    /// Returns whether the key is ephemeral alongside the ring.
+    pub fn from_env() -> Result<(Self, bool), String> {
+        match env::var("VERIFIER_SIGNING_KEY") {
+            Ok(hex_seed) => {
+                let created_at = match env::var("VERIFIER_SIGNING_KEY_CREATED_AT") {
+                    Ok(created_at) => Some(created_at.trim().parse().map_err(|_| {
+                        "VERIFIER_SIGNING_KEY_CREATED_AT must be unix seconds".to_string()
+                    })?),
+                    Err(_) => None,
+                };
+                Ok((Self::from_hex(&hex_seed, created_at)?, false))
+            }
+            Err(_) => Ok((Self::generate(), true)),
+        }
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.signing.verifying_key()
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        self.signing.sign(message)
+    }
+
+    /// Is `kid` the active key or a retired key that still verifies?
+    pub fn knows(&self, kid: &str) -> bool {
+        self.key_for(kid).is_some()
+    }
+
+    pub fn retired_kids(&self) -> impl Iterator<Item = &str> {
+        self.retired.iter().map(|(kid, _)| kid.as_str())
+    }
+
+    fn key_for(&self, kid: &str) -> Option<VerifyingKey> {
+        if kid == self.kid {
+            return Some(self.verifying_key());
+        }
+        self.retired
+            .iter()
+            .find(|(retired, _)| retired == kid)
+            .map(|(_, key)| *key)
+    }
+
+    /// Verify `signature` with the key named `kid`.  `false` for unknown kids.
+    pub fn verify(&self, kid: &str, message: &[u8], signature: &Signature) -> bool {
+        self.key_for(kid)
+            .is_some_and(|key| key.verify(message, signature).is_ok())
+    }
+
+    /// A ring signing with `next`.  This ring's keys keep verifying unless
+    /// `retire_previous` is set.
+    fn rotate_to(&self, mut next: KeyRing, retire_previous: bool) -> KeyRing {
+        if !retire_previous {
+            next.retired.push((self.kid.clone(), self.verifying_key()));
+            next.retired.extend(self.retired.iter().cloned());
+            next.retired.truncate(MAX_RETIRED_KEYS);
+        }
+        next
+    }
+}
+
+/// The active key ring, swapped atomically on rotation.
+pub struct SigningKeys {
+    current: RwLock<Arc<KeyRing>>,
+}
+
+impl SigningKeys {
+    pub fn new(ring: KeyRing) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(ring)),
+        }
+    }
+
+    pub fn current(&self) -> Arc<KeyRing> {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Start signing with `next`.  Returns the new ring, or `None` when
+    /// `next` is already the active or a retired key.
+    pub fn rotate(&self, next: KeyRing, retire_previous: bool) -> Option<Arc<KeyRing>> {
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        if current.knows(next.kid()) {
+            return None;
+        }
+        *current = Arc::new(current.rotate_to(next, retire_previous));
+        Some(current.clone())
+    }
+}
+
+/// Key id: first 16 hex chars of SHA-256 over the public key.
+pub fn fingerprint(key: &VerifyingKey) -> String {
+    let digest = Sha256::digest(key.as_bytes());
+    hex::encode(&digest[..8])
+}
+
+fn parse_seed(hex_seed: &str) -> Result<[u8; 32], String> {
+    let bytes = hex::decode(hex_seed.trim())
+        .map_err(|e| format!("VERIFIER_SIGNING_KEY is not hex: {e}"))?;
+    bytes
+        .try_into()
+        .map_err(|_| "VERIFIER_SIGNING_KEY must be a 32-byte (64 hex char) seed".to_string())
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kid_is_stable_per_seed() {
+        let a = KeyRing::from_seed([7; 32]);
+        let b = KeyRing::from_seed([7; 32]);
+        let c = KeyRing::from_seed([8; 32]);
+        assert_eq!(a.kid(), b.kid());
+        assert_ne!(a.kid(), c.kid());
+        assert_eq!(a.kid().len(), 16);
+    }
+
+    #[test]
+    fn signs_and_verifies() {
+        let ring = KeyRing::generate();
+        let sig = ring.sign(b"hello");
+        assert!(ring.verify(ring.kid(), b"hello", &sig));
+        assert!(!ring.verify(ring.kid(), b"hullo", &sig));
+        assert!(!ring.verify("0000000000000000", b"hello", &sig));
+    }
+
+    #[test]
+    fn rotation_keeps_previous_keys_verifying() {
+        let keys = SigningKeys::new(KeyRing::from_seed([1; 32]));
+        let old = keys.current();
+        let sig = old.sign(b"hello");
+
+        let rotated = keys.rotate(KeyRing::from_seed([2; 32]), false).unwrap();
+        assert_ne!(rotated.kid(), old.kid());
+        assert!(rotated.verify(old.kid(), b"hello", &sig));
+        assert_eq!(rotated.retired_kids().collect::<Vec<_>>(), [old.kid()]);
+
+        assert!(keys.rotate(KeyRing::from_seed([1; 32]), false).is_none());
+        for _ in 0..MAX_RETIRED_KEYS {
+            keys.rotate(KeyRing::generate(), false).unwrap();
+        }
+        assert!(!keys.current().knows(old.kid()));
+    }
+
+    #[test]
+    fn rotation_can_retire_previous_keys() {
+        let keys = SigningKeys::new(KeyRing::from_seed([1; 32]));
+        let old = keys.current();
+        keys.rotate(KeyRing::from_seed([2; 32]), false).unwrap();
+        let rotated = keys.rotate(KeyRing::from_seed([3; 32]), true).unwrap();
+        assert!(!rotated.knows(old.kid()));
+        assert_eq!(rotated.retired_kids().count(), 0);
+        assert!(rotated.verify(rotated.kid(), b"x", &rotated.sign(b"x")));
+    }
+
+    #[test]
+    fn seed_parsing() {
+        assert_eq!(parse_seed(&"ab".repeat(32)).unwrap(), [0xab; 32]);
+        assert!(parse_seed("abcd").is_err());
+        assert!(parse_seed("zz").is_err());
+    }
+}
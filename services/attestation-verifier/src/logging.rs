@@ -0,0 +1,377 @@
+//! Structured JSON logs with request IDs and redaction.
+//!
+//! Every line is one JSON object on stdout: `timestamp`, `level`, `target`,
+//! `environment`, `message`, the event's `fields`, the enclosing `spans`,
+//! and — inside a request — its `request_id`.  Verbosity follows
+//! `RUST_LOG` (default `info`).
+//!
+//! Redaction happens in the formatter, so it holds no matter how a call
+//! site logs: any field named in `SENSITIVE_FIELDS` — on an event or a span
+//! — is replaced by a short SHA-256 prefix (`sha256:1a2b3c4d5e6f`).  Per the
+//! data-topology spec, device keys, integrity tokens, nullifiers and
+//! district hashes are never written in clear; the prefix is enough to
+//! correlate lines without being reversible for high-entropy values.
+//!
+//! Trace export (see `telemetry`) sees raw field values, so call sites pass
+//! sensitive values as `Redacted(..)`; the formatter keeps such values as
+//! they are instead of hashing them twice.
+//!
+//! Each request gets an ID from a valid incoming `X-Request-Id` header or a
+//! fresh random one; it is recorded on the request span and echoed back.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use tracing::field::{Field, Visit};
+use tracing::{span, Event, Span, Subscriber};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use warp::Filter;
+
+use crate::telemetry;
+
+/// Header carrying the request ID in both directions.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest accepted incoming request ID.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Field names whose values are always hashed before they are written.
+pub const SENSITIVE_FIELDS: &[&str] = &[
+    "device_key",
+    "integrity_token",
+    "nullifier",
+    "principal_nullifier",
+    "sub",
+    "district_hash",
+    "expected_district_hash",
+    "token",
+];
+
+const REDACTION_PREFIX: &str = "sha256:";
+
+/// Hex digits of SHA-256 kept by `redact`.
+const REDACTION_HEX_LEN: usize = 12;
+
+/// Short, stable stand-in for a sensitive value.
+pub fn redact(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    format!("{REDACTION_PREFIX}{}", &hex::encode(digest)[..REDACTION_HEX_LEN])
+}
+
+fn is_redacted(value: &str) -> bool {
+    value.strip_prefix(REDACTION_PREFIX).is_some_and(|digits| {
+        digits.len() == REDACTION_HEX_LEN && digits.bytes().all(|b| b.is_ascii_hexdigit())
+    })
+}
+
+/// Displays as `redact(value)`; use for sensitive tracing fields.
+pub struct Redacted<'a>(pub &'a str);
+
+impl fmt::Display for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&redact(self.0))
+    }
+}
+
+/// Install the JSON subscriber, plus OTLP export when configured.  Call
+/// once, first thing in `main`; logging works even if export fails.
+pub fn init(environment: &'static str) -> Result<(), String> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (tracer, export_error) = match telemetry::tracer_from_env() {
+        Ok(tracer) => (tracer, None),
+        Err(e) => (None, Some(e)),
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .fmt_fields(RedactingFields)
+                .event_format(JsonEvents { environment }),
+        )
+        .with(tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)))
+        .init();
+    export_error.map_or(Ok(()), Err)
+}
+
+// ── request IDs ────────────────────────────────────────────────────────
+
+/// Span wrapping one request; `request_id` is filled in by `request_id()`.
+/// A W3C `traceparent` on the request becomes the span's remote parent.
+pub fn request_span(info: warp::trace::Info<'_>) -> Span {
+    let span = tracing::info_span!(
+        "request",
+        method = %info.method(),
+        path = %info.path(),
+        request_id = tracing::field::Empty,
+    );
+    span.set_parent(telemetry::remote_context(info.request_headers()));
+    span
+}
+
+/// The caller's `X-Request-Id` when well-formed, else a fresh one.
+pub fn request_id() -> impl Filter<Extract = (String,), Error = std::convert::Infallible> + Clone {
+    warp::header::optional::<String>(REQUEST_ID_HEADER)
+        .or(warp::any().map(|| None))
+        .unify()
+        .map(|incoming: Option<String>| {
+            let id = incoming
+                .filter(|id| is_valid_request_id(id))
+                .unwrap_or_else(|| hex::encode(rand::random::<[u8; 16]>()));
+            Span::current().record("request_id", id.as_str());
+            id
+        })
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+// ── formatting ─────────────────────────────────────────────────────────
+
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl JsonVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: Value) {
+        let name = field.name();
+        let value = if SENSITIVE_FIELDS.contains(&name) {
+            let clear = match value {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            if is_redacted(&clear) {
+                Value::String(clear)
+            } else {
+                Value::String(redact(&clear))
+            }
+        } else {
+            value
+        };
+        self.0.insert(name.to_string(), value);
+    }
+}
+
+impl Visit for JsonVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, Value::from(format!("{value:?}")));
+    }
+}
+
+/// Span fields, stored as (redacted) JSON objects.
+pub struct RedactingFields;
+
+impl<'writer> FormatFields<'writer> for RedactingFields {
+    fn format_fields<R: RecordFields>(&self, mut writer: Writer<'writer>, fields: R) -> fmt::Result {
+        let mut map = Map::new();
+        fields.record(&mut JsonVisitor(&mut map));
+        write!(writer, "{}", Value::Object(map))
+    }
+
+    fn add_fields(&self, current: &mut FormattedFields<Self>, fields: &span::Record<'_>) -> fmt::Result {
+        let mut map: Map<String, Value> = serde_json::from_str(&current.fields).unwrap_or_default();
+        fields.record(&mut JsonVisitor(&mut map));
+        current.fields = Value::Object(map).to_string();
+        Ok(())
+    }
+}
+
+/// One JSON object per event.
+pub struct JsonEvents {
+    pub environment: &'static str,
+}
+
+impl<S, N> FormatEvent<S, N> for JsonEvents
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'writer> FormatFields<'writer> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let meta = event.metadata();
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            Value::from(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+        );
+        line.insert("level".to_string(), Value::from(meta.level().as_str()));
+        line.insert("target".to_string(), Value::from(meta.target()));
+        line.insert("environment".to_string(), Value::from(self.environment));
+        if let Some(message) = fields.remove("message") {
+            line.insert("message".to_string(), message);
+        }
+
+        let mut spans = Vec::new();
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let extensions = span.extensions();
+                let mut entry: Map<String, Value> = extensions
+                    .get::<FormattedFields<N>>()
+                    .and_then(|f| serde_json::from_str(&f.fields).ok())
+                    .unwrap_or_default();
+                if let Some(id) = entry.get("request_id") {
+                    line.insert("request_id".to_string(), id.clone());
+                }
+                entry.insert("name".to_string(), Value::from(span.name()));
+                spans.push(Value::Object(entry));
+            }
+        }
+        if !fields.is_empty() {
+            line.insert("fields".to_string(), Value::Object(fields));
+        }
+        if !spans.is_empty() {
+            line.insert("spans".to_string(), Value::Array(spans));
+        }
+
+        let mut out = Value::Object(line).to_string();
+        out.push('\n');
+        writer.write_str(&out)
+    }
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run `f` under the JSON subscriber and return the parsed lines.
+    fn capture(f: impl FnOnce()) -> Vec<Value> {
+        let buffer = Capture::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .fmt_fields(RedactingFields)
+            .event_format(JsonEvents { environment: "DEV" })
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, f);
+        let bytes = buffer.0.lock().unwrap().clone();
+        String::from_utf8(bytes)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sensitive_fields_are_hashed_on_events_and_spans() {
+        let lines = capture(|| {
+            let span = tracing::info_span!("verify", device_key = "dGVzdC1kZXZpY2Uta2V5", request_id = "req-1");
+            let _entered = span.enter();
+            tracing::info!(
+                nullifier = %"nullifier-abc",
+                integrity_token = "secret-token",
+                district_hash = "d1",
+                platform = "web",
+                trust_score = 0.9,
+                "session issued"
+            );
+        });
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        let text = line.to_string();
+        for secret in ["dGVzdC1kZXZpY2Uta2V5", "nullifier-abc", "secret-token", "\"d1\""] {
+            assert!(!text.contains(secret), "{secret} leaked: {text}");
+        }
+        assert_eq!(line["message"], "session issued");
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["environment"], "DEV");
+        assert_eq!(line["request_id"], "req-1");
+        assert_eq!(line["fields"]["nullifier"], redact("nullifier-abc"));
+        assert_eq!(line["fields"]["platform"], "web");
+        assert_eq!(line["fields"]["trust_score"], 0.9);
+        assert_eq!(line["spans"][0]["name"], "verify");
+        assert_eq!(line["spans"][0]["device_key"], redact("dGVzdC1kZXZpY2Uta2V5"));
+    }
+
+    #[test]
+    fn fields_recorded_later_are_merged_and_redacted() {
+        let lines = capture(|| {
+            let span = tracing::info_span!("request", nullifier = tracing::field::Empty, path = "/verify");
+            span.record("nullifier", "nullifier-late");
+            span.in_scope(|| tracing::info!("done"));
+        });
+        assert_eq!(lines[0]["spans"][0]["path"], "/verify");
+        assert_eq!(lines[0]["spans"][0]["nullifier"], redact("nullifier-late"));
+    }
+
+    #[test]
+    fn redaction_is_a_stable_prefix() {
+        assert_eq!(redact("abc"), redact("abc"));
+        assert_ne!(redact("abc"), redact("abd"));
+        assert_eq!(redact("abc").len(), "sha256:".len() + 12);
+        assert!(is_redacted(&redact("abc")));
+        assert!(!is_redacted("sha256:not-a-digest"));
+    }
+
+    #[test]
+    fn pre_redacted_values_are_not_hashed_twice() {
+        let lines = capture(|| {
+            tracing::info!(nullifier = %Redacted("nullifier-abc"), "issued");
+        });
+        assert_eq!(lines[0]["fields"]["nullifier"], redact("nullifier-abc"));
+    }
+
+    #[test]
+    fn request_ids_are_validated() {
+        assert!(is_valid_request_id("3f2c-aa_01.b:7"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("has space"));
+        assert!(!is_valid_request_id("line\nbreak"));
+        assert!(!is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+    }
+}
@@ -0,0 +1,317 @@
+//! Residency-set Merkle inclusion proofs.
+//!
+//! The residency set is a binary Merkle tree whose leaves commit to
+//! `(district_hash, nullifier)`.  A constituency claim carries the sibling
+//! path and leaf index; folding the path from the recomputed leaf must land
+//! on the proof's `merkle_root`, which the root registry vouches for.
+//!
+//! Two hash layouts are supported, selected by `RESIDENCY_TREE_HASH`:
+//!
+//! - `sha256` (default): `leaf = H(0x00 ‖ len ‖ district ‖ len ‖ nullifier)`,
+//!   `node = H(0x01 ‖ left ‖ right)`, lengths as u32 big-endian.
+//! - `poseidon`: circomlib Poseidon over BN254, so roots match a circom
+//!   tree.  Strings enter the field as the first 31 bytes of their SHA-256
+//!   (always below the modulus); `leaf = P(district, nullifier)`,
+//!   `node = P(left, right)`.
+//!
+//! Nodes and roots are 32 bytes, written as hex (an `0x` prefix is allowed).
+
+use ark_bn254::Fr;
+use light_poseidon::{Poseidon, PoseidonBytesHasher};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+pub type Node = [u8; 32];
+
+/// Deepest tree accepted; 2^32 leaves is far beyond any residency set.
+pub const MAX_TREE_DEPTH: usize = 32;
+
+pub trait TreeHasher {
+    /// Commitment to one resident's `(district_hash, nullifier)`.
+    fn leaf(&self, district_hash: &str, nullifier: &str) -> Node;
+    /// Parent of two nodes; `None` if an input is not valid for the layout.
+    fn node(&self, left: &Node, right: &Node) -> Option<Node>;
+}
+
+pub struct Sha256Tree;
+
+impl TreeHasher for Sha256Tree {
+    fn leaf(&self, district_hash: &str, nullifier: &str) -> Node {
+        let mut hasher = Sha256::new();
+        hasher.update([0x00]);
+        for part in [district_hash, nullifier] {
+            hasher.update((part.len() as u32).to_be_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher.finalize().into()
+    }
+
+    fn node(&self, left: &Node, right: &Node) -> Option<Node> {
+        let mut hasher = Sha256::new();
+        hasher.update([0x01]);
+        hasher.update(left);
+        hasher.update(right);
+        Some(hasher.finalize().into())
+    }
+}
+
+pub struct PoseidonTree;
+
+impl PoseidonTree {
+    /// Map a string into the BN254 scalar field.
+    fn field_element(value: &str) -> Node {
+        let digest = Sha256::digest(value.as_bytes());
+        let mut element = [0u8; 32];
+        element[1..].copy_from_slice(&digest[..31]);
+        element
+    }
+
+    fn hash(inputs: &[&[u8]]) -> Option<Node> {
+        let mut poseidon = Poseidon::<Fr>::new_circom(inputs.len()).ok()?;
+        poseidon.hash_bytes_be(inputs).ok()
+    }
+}
+
+impl TreeHasher for PoseidonTree {
+    fn leaf(&self, district_hash: &str, nullifier: &str) -> Node {
+        let district = Self::field_element(district_hash);
+        let nullifier = Self::field_element(nullifier);
+        Self::hash(&[&district, &nullifier]).expect("field elements are below the modulus")
+    }
+
+    /// `None` when a sibling is not a canonical field element.
+    fn node(&self, left: &Node, right: &Node) -> Option<Node> {
+        Self::hash(&[left, right])
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TreeHash {
+    #[default]
+    Sha256,
+    Poseidon,
+}
+
+impl TreeHash {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "sha256" | "sha-256" => Some(TreeHash::Sha256),
+            "poseidon" => Some(TreeHash::Poseidon),
+            _ => None,
+        }
+    }
+
+    pub fn hasher(self) -> &'static dyn TreeHasher {
+        match self {
+            TreeHash::Sha256 => &Sha256Tree,
+            TreeHash::Poseidon => &PoseidonTree,
+        }
+    }
+}
+
+/// Sibling path from leaf to root.  Bit `i` of `index` says whether the
+/// running node is the right (1) or left (0) child at level `i`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct InclusionProof {
+    pub index: u64,
+    pub path: Vec<String>,
+}
+
+/// The proof could not be evaluated at all (bad hex, depth, index).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MalformedInclusion;
+
+impl InclusionProof {
+    /// Fold the path from `leaf` and return the implied root.
+    pub fn root(&self, hasher: &dyn TreeHasher, leaf: Node) -> Result<Node, MalformedInclusion> {
+        let depth = self.path.len();
+        if depth > MAX_TREE_DEPTH || self.index >> depth != 0 {
+            return Err(MalformedInclusion);
+        }
+        let mut current = leaf;
+        for (level, sibling) in self.path.iter().enumerate() {
+            let sibling = parse_node(sibling).ok_or(MalformedInclusion)?;
+            let parent = if self.index >> level & 1 == 0 {
+                hasher.node(&current, &sibling)
+            } else {
+                hasher.node(&sibling, &current)
+            };
+            current = parent.ok_or(MalformedInclusion)?;
+        }
+        Ok(current)
+    }
+
+    /// Does the path place `(district_hash, nullifier)` under `root`?
+    pub fn verify(
+        &self,
+        hasher: &dyn TreeHasher,
+        district_hash: &str,
+        nullifier: &str,
+        root: &str,
+    ) -> Result<bool, MalformedInclusion> {
+        let computed = self.root(hasher, hasher.leaf(district_hash, nullifier))?;
+        Ok(parse_node(root) == Some(computed))
+    }
+}
+
+/// Parse 32 bytes of hex, with or without `0x`.
+pub fn parse_node(value: &str) -> Option<Node> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(digits).ok()?.try_into().ok()
+}
+
+/// Test-side tree builder; an odd node at any level is paired with zeros.
+#[cfg(test)]
+pub struct MerkleTree {
+    levels: Vec<Vec<Node>>,
+}
+
+#[cfg(test)]
+impl MerkleTree {
+    pub fn build(hasher: &dyn TreeHasher, leaves: Vec<Node>) -> Self {
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels.last().unwrap();
+            let parents = level
+                .chunks(2)
+                .map(|pair| hasher.node(&pair[0], pair.get(1).unwrap_or(&[0; 32])).unwrap())
+                .collect();
+            levels.push(parents);
+        }
+        Self { levels }
+    }
+
+    pub fn root_hex(&self) -> String {
+        hex::encode(self.levels.last().unwrap()[0])
+    }
+
+    pub fn proof(&self, index: usize) -> InclusionProof {
+        let path = self.levels[..self.levels.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(level, nodes)| {
+                let sibling = (index >> level) ^ 1;
+                hex::encode(nodes.get(sibling).unwrap_or(&[0; 32]))
+            })
+            .collect();
+        InclusionProof {
+            index: index as u64,
+            path,
+        }
+    }
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn residents(hasher: &dyn TreeHasher) -> (Vec<(String, String)>, MerkleTree) {
+        let residents: Vec<_> = (0..5)
+            .map(|i| (format!("district-{}", i % 2), format!("nullifier-{i}")))
+            .collect();
+        let leaves = residents.iter().map(|(d, n)| hasher.leaf(d, n)).collect();
+        (residents, MerkleTree::build(hasher, leaves))
+    }
+
+    #[test]
+    fn every_leaf_verifies_under_both_layouts() {
+        for layout in [TreeHash::Sha256, TreeHash::Poseidon] {
+            let hasher = layout.hasher();
+            let (residents, tree) = residents(hasher);
+            for (i, (district, nullifier)) in residents.iter().enumerate() {
+                let proof = tree.proof(i);
+                assert_eq!(proof.path.len(), 3);
+                assert_eq!(
+                    proof.verify(hasher, district, nullifier, &tree.root_hex()),
+                    Ok(true),
+                    "{layout:?} leaf {i}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_claims_do_not_verify() {
+        let hasher = TreeHash::Sha256.hasher();
+        let (_, tree) = residents(hasher);
+        let root = tree.root_hex();
+        let proof = tree.proof(1);
+        assert_eq!(proof.verify(hasher, "district-0", "nullifier-1", &root), Ok(false));
+        assert_eq!(proof.verify(hasher, "district-1", "nullifier-2", &root), Ok(false));
+
+        let mut shifted = proof.clone();
+        shifted.index = 3;
+        assert_eq!(shifted.verify(hasher, "district-1", "nullifier-1", &root), Ok(false));
+
+        // A SHA-256 path never verifies under the Poseidon layout.
+        let poseidon = TreeHash::Poseidon.hasher();
+        assert_ne!(proof.verify(poseidon, "district-1", "nullifier-1", &root), Ok(true));
+    }
+
+    #[test]
+    fn malformed_paths_are_reported() {
+        let hasher = TreeHash::Sha256.hasher();
+        let bad_hex = InclusionProof {
+            index: 0,
+            path: vec!["zz".to_string()],
+        };
+        assert_eq!(bad_hex.verify(hasher, "d", "n", "00"), Err(MalformedInclusion));
+
+        let index_out_of_range = InclusionProof {
+            index: 2,
+            path: vec![hex::encode([0; 32])],
+        };
+        assert_eq!(index_out_of_range.verify(hasher, "d", "n", "00"), Err(MalformedInclusion));
+
+        let too_deep = InclusionProof {
+            index: 0,
+            path: vec![hex::encode([0; 32]); MAX_TREE_DEPTH + 1],
+        };
+        assert_eq!(too_deep.verify(hasher, "d", "n", "00"), Err(MalformedInclusion));
+
+        // Above the BN254 modulus: not a field element.
+        let out_of_field = InclusionProof {
+            index: 0,
+            path: vec![hex::encode([0xff; 32])],
+        };
+        assert_eq!(
+            out_of_field.verify(TreeHash::Poseidon.hasher(), "d", "n", "00"),
+            Err(MalformedInclusion)
+        );
+    }
+
+    #[test]
+    fn poseidon_matches_circomlib() {
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        let mut two = [0u8; 32];
+        two[31] = 2;
+        assert_eq!(
+            hex::encode(PoseidonTree.node(&one, &two).unwrap()),
+            "115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a"
+        );
+    }
+
+    #[test]
+    fn single_leaf_tree_is_its_own_root() {
+        let hasher = TreeHash::Sha256.hasher();
+        let leaf = hasher.leaf("district-0", "nullifier-0");
+        let tree = MerkleTree::build(hasher, vec![leaf]);
+        assert!(tree.proof(0).path.is_empty());
+        assert_eq!(
+            tree.proof(0).verify(hasher, "district-0", "nullifier-0", &format!("0x{}", tree.root_hex())),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn layout_names() {
+        assert_eq!(TreeHash::parse("SHA-256"), Some(TreeHash::Sha256));
+        assert_eq!(TreeHash::parse("poseidon"), Some(TreeHash::Poseidon));
+        assert_eq!(TreeHash::parse("keccak"), None);
+    }
+}
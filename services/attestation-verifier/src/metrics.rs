@@ -0,0 +1,268 @@
+//! Prometheus metrics, served at `GET /metrics`.
+//!
+//! Labels are limited to bounded, non-identifying values — route templates,
+//! methods, status codes, platforms, outcomes and reason codes.  Nullifiers,
+//! device keys, IPs and tokens must never become label values.
+
+use std::time::Duration;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+/// Every path the router serves, without the `/v1` prefix; anything else is
+/// reported as `unmatched`.
+const ROUTES: &[&str] = &[
+    "/health",
+    "/livez",
+    "/readyz",
+    "/metrics",
+    "/openapi.json",
+    "/verify",
+    "/challenge",
+    "/policy/dry-run",
+    "/policy/thresholds",
+    "/authorize",
+    "/constituency/verify",
+    "/residency/roots",
+    "/district/hash",
+    "/district/hashes",
+    "/transparency/sth",
+    "/transparency/entries",
+    "/transparency/proof/inclusion",
+    "/transparency/proof/consistency",
+    "/delegation/grants",
+    "/delegation/verify",
+    "/delegation/revoke",
+    "/budget/check",
+    "/budget/consume",
+    "/admin/sessions/revoke",
+    "/admin/keys/rotate",
+    "/admin/policy/reload",
+    "/admin/anchors/reload",
+    "/admin/residency/roots",
+    "/admin/device-keys/inspect",
+    "/admin/rate-limits",
+];
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    verifications: IntCounterVec,
+    trust_scores: HistogramVec,
+    mock_verifications: IntCounterVec,
+    nonce_rejections: IntCounterVec,
+    rate_limited: IntCounterVec,
+    signing_key_age: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("verifier".to_string()), None)
+            .expect("static registry prefix is valid");
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route, method and status"),
+            &["route", "method", "status"],
+        )
+        .expect("static metric definition");
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency")
+                .buckets(vec![0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0]),
+            &["route", "method"],
+        )
+        .expect("static metric definition");
+        let verifications = IntCounterVec::new(
+            Opts::new(
+                "verifications_total",
+                "Attestation verifications by platform and outcome (assurance level or rejected)",
+            ),
+            &["platform", "outcome"],
+        )
+        .expect("static metric definition");
+        let trust_scores = HistogramVec::new(
+            HistogramOpts::new("trust_score", "Trust scores of issued sessions")
+                .buckets((1..=10).map(|i| f64::from(i) / 10.0).collect()),
+            &["platform"],
+        )
+        .expect("static metric definition");
+        let mock_verifications = IntCounterVec::new(
+            Opts::new("mock_verifications_total", "Verifications answered in mock mode"),
+            &["platform"],
+        )
+        .expect("static metric definition");
+        let nonce_rejections = IntCounterVec::new(
+            Opts::new(
+                "nonce_rejections_total",
+                "Challenge nonces refused (missing, expired, replayed, ...)",
+            ),
+            &["reason"],
+        )
+        .expect("static metric definition");
+        let rate_limited = IntCounterVec::new(
+            Opts::new("rate_limited_total", "Requests refused by a rate-limit bucket"),
+            &["dimension"],
+        )
+        .expect("static metric definition");
+        let signing_key_age = IntGauge::new(
+            "signing_key_age_seconds",
+            "Seconds since the session signing key was created",
+        )
+        .expect("static metric definition");
+
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_duration.clone()),
+            Box::new(verifications.clone()),
+            Box::new(trust_scores.clone()),
+            Box::new(mock_verifications.clone()),
+            Box::new(nonce_rejections.clone()),
+            Box::new(rate_limited.clone()),
+            Box::new(signing_key_age.clone()),
+        ] {
+            registry.register(collector).expect("metric names are unique");
+        }
+        Self {
+            registry,
+            http_requests,
+            http_duration,
+            verifications,
+            trust_scores,
+            mock_verifications,
+            nonce_rejections,
+            rate_limited,
+            signing_key_age,
+        }
+    }
+
+    pub fn observe_request(&self, path: &str, method: &str, status: u16, elapsed: Duration) {
+        let route = route_label(path);
+        let method = method_label(method);
+        self.http_requests
+            .with_label_values(&[route, method, &status.to_string()])
+            .inc();
+        self.http_duration
+            .with_label_values(&[route, method])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// An issued session: `outcome` is its assurance level.
+    pub fn observe_session(&self, platform: &str, outcome: &str, trust_score: f32, mock: bool) {
+        self.verifications.with_label_values(&[platform, outcome]).inc();
+        self.trust_scores
+            .with_label_values(&[platform])
+            .observe(f64::from(trust_score));
+        if mock {
+            self.mock_verifications.with_label_values(&[platform]).inc();
+        }
+    }
+
+    pub fn observe_rejected_verification(&self, platform: &str) {
+        self.verifications
+            .with_label_values(&[platform, "rejected"])
+            .inc();
+    }
+
+    /// `reason` is an error code such as `POW_REPLAYED`.
+    pub fn observe_nonce_rejection(&self, reason: &str) {
+        self.nonce_rejections.with_label_values(&[reason]).inc();
+    }
+
+    pub fn observe_rate_limited(&self, dimension: &str) {
+        self.rate_limited.with_label_values(&[dimension]).inc();
+    }
+
+    /// Prometheus text exposition, with gauges refreshed for `now`.
+    pub fn render(&self, signing_key_created_at: u64, now: u64) -> String {
+        self.signing_key_age
+            .set(now.saturating_sub(signing_key_created_at) as i64);
+        let mut out = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut out)
+            .expect("text encoding cannot fail");
+        String::from_utf8(out).expect("text exposition is UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Route template for `path`.  `/v1/verify` and its deprecated alias
+/// `/verify` share one label, so dashboards survive the migration.
+pub fn route_label(path: &str) -> &'static str {
+    let path = path.strip_suffix('/').filter(|p| !p.is_empty()).unwrap_or(path);
+    let path = path.strip_prefix("/v1").filter(|p| p.starts_with('/')).unwrap_or(path);
+    ROUTES
+        .iter()
+        .find(|route| **route == path)
+        .copied()
+        .unwrap_or("unmatched")
+}
+
+/// Standard methods keep their name; hyper accepts any extension token, so
+/// everything else shares one label.
+fn method_label(method: &str) -> &'static str {
+    const METHODS: &[&str] = &["GET", "POST", "HEAD", "PUT", "DELETE", "OPTIONS", "PATCH"];
+    METHODS
+        .iter()
+        .find(|m| **m == method)
+        .copied()
+        .unwrap_or("other")
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_paths_collapse_to_one_label() {
+        assert_eq!(route_label("/verify"), "/verify");
+        assert_eq!(route_label("/verify/"), "/verify");
+        assert_eq!(route_label("/nullifier-abc123"), "unmatched");
+        assert_eq!(route_label("/"), "unmatched");
+    }
+
+    #[test]
+    fn versioned_paths_share_the_alias_label() {
+        assert_eq!(route_label("/v1/verify"), "/verify");
+        assert_eq!(route_label("/v1/admin/keys/rotate/"), "/admin/keys/rotate");
+        assert_eq!(route_label("/v1"), "unmatched");
+        assert_eq!(route_label("/v1verify"), "unmatched");
+    }
+
+    #[test]
+    fn invented_methods_collapse_to_one_label() {
+        let metrics = Metrics::new();
+        metrics.observe_request("/verify", "FROBNICATE", 404, Duration::from_millis(1));
+        metrics.observe_request("/verify", "XYZZY", 404, Duration::from_millis(1));
+        let text = metrics.render(0, 0);
+        assert!(text.contains(
+            r#"verifier_http_requests_total{method="other",route="/verify",status="404"} 2"#
+        ));
+        assert!(!text.contains("FROBNICATE"));
+        assert_eq!(method_label("PATCH"), "PATCH");
+        assert_eq!(method_label("get"), "other");
+    }
+
+    #[test]
+    fn renders_text_exposition() {
+        let metrics = Metrics::new();
+        metrics.observe_request("/verify", "POST", 200, Duration::from_millis(3));
+        metrics.observe_session("web", "silver", 0.85, true);
+        metrics.observe_rate_limited("ip");
+        let text = metrics.render(1_000, 1_060);
+        assert!(text.contains(
+            r#"verifier_http_requests_total{method="POST",route="/verify",status="200"} 1"#
+        ));
+        assert!(text.contains(r#"verifier_verifications_total{outcome="silver",platform="web"} 1"#));
+        assert!(text.contains(r#"verifier_trust_score_bucket{platform="web",le="0.9"} 1"#));
+        assert!(text.contains(r#"verifier_mock_verifications_total{platform="web"} 1"#));
+        assert!(text.contains(r#"verifier_rate_limited_total{dimension="ip"} 1"#));
+        assert!(text.contains("verifier_signing_key_age_seconds 60"));
+    }
+}
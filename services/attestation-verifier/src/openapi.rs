@@ -0,0 +1,100 @@
+//! OpenAPI 3 description of the `/v1` API, served at `/openapi.json`.
+//!
+//! Generated from the request/response types and the handlers'
+//! `#[utoipa::path]` attributes, so it cannot drift from what serde
+//! actually reads and writes; `packages/types` can be generated or checked
+//! against it.  Probes (`/livez`, `/readyz`, `/metrics`) and the operator
+//! API under `/admin` are deliberately left out.
+
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Attestation Verifier",
+        description = "DEV-ONLY stub: responses carry `environment: \"DEV\"` and do not \
+                       provide production sybil defense.  Unversioned paths (`/verify`, \
+                       `/health`, ...) are deprecated aliases of the `/v1` paths."
+    ),
+    paths(
+        crate::handle_health,
+        crate::handle_verify,
+        crate::handle_challenge,
+        crate::handle_policy_dry_run,
+        crate::handle_thresholds,
+        crate::handle_authorize,
+        crate::handle_constituency_verify,
+        crate::handle_residency_roots,
+        crate::handle_publish_root,
+        crate::handle_district_hash,
+        crate::handle_district_list,
+        crate::handle_tree_head,
+        crate::handle_log_entries,
+        crate::handle_inclusion_proof,
+        crate::handle_consistency_proof,
+        crate::handle_grant_issue,
+        crate::handle_delegation_verify,
+        crate::handle_grant_revoke,
+        crate::handle_budget_check,
+        crate::handle_budget_consume,
+    ),
+    tags(
+        (name = "attestation", description = "Device attestation and sessions"),
+        (name = "policy", description = "Trust policy and gates"),
+        (name = "residency", description = "Residency roots and constituency proofs"),
+        (name = "district", description = "District hashing"),
+        (name = "transparency", description = "Transparency log of issued sessions"),
+        (name = "delegation", description = "Familiar delegation grants"),
+        (name = "budget", description = "Daily action budgets"),
+        (name = "health", description = "Deployment information"),
+    )
+)]
+struct ApiDoc;
+
+pub fn document() -> utoipa::openapi::OpenApi {
+    ApiDoc::openapi()
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics;
+
+    #[test]
+    fn every_documented_path_is_versioned_and_routed() {
+        let doc = document();
+        assert!(doc.paths.paths.len() >= 19);
+        for path in doc.paths.paths.keys() {
+            let unversioned = path.strip_prefix("/v1").expect("documented paths are versioned");
+            assert_ne!(metrics::route_label(unversioned), "unmatched", "{path}");
+        }
+    }
+
+    #[test]
+    fn wire_types_match_the_ts_contract() {
+        let doc = serde_json::to_value(document()).unwrap();
+        let schemas = &doc["components"]["schemas"];
+        let payload = &schemas["AttestationPayload"];
+        let mut required: Vec<_> = payload["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_str().unwrap())
+            .collect();
+        required.sort_unstable();
+        assert_eq!(required, ["deviceKey", "integrityToken", "nonce", "platform"]);
+        assert!(payload["properties"].get("unknown").is_none());
+        assert_eq!(
+            schemas["Platform"]["enum"],
+            serde_json::json!(["ios", "android", "web"])
+        );
+        for field in ["token", "trustScore", "scaledTrustScore", "nullifier", "createdAt", "expiresAt"] {
+            assert!(
+                schemas["SessionResponse"]["properties"].get(field).is_some(),
+                "SessionResponse.{field}"
+            );
+        }
+    }
+}
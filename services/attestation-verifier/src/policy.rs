@@ -0,0 +1,447 @@
+//! Declarative trust-scoring policy.
+//!
+//! Maps verifier signals to a trust score per platform.  The built-in
+//! policy lives in `policy/default.json`; operators can point
+//! `TRUST_POLICY_PATH` at their own file, which is re-read whenever its
+//! modification time changes (every `TRUST_POLICY_RELOAD_SECS`, default 10).
+//!
+//! A platform policy is a list of rules and caps:
+//!
+//! - a **rule** fires when its `signal` is present, every code in
+//!   `requires` is present and no code in `unless` is; its `weight` is added
+//!   to the score and attributed to that signal in the verdict;
+//! - a **cap** limits the score to `max` when its `signal` is present;
+//! - `max` (default 1.0) bounds the platform's score overall.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::verdict::{assurance_for, ReasonCode, Signal, Verdict};
+use crate::Platform;
+
+/// Policy compiled into the binary; used when no file is configured.
+const DEFAULT_POLICY_JSON: &str = include_str!("../policy/default.json");
+
+/// Default interval between modification-time checks of the policy file.
+const DEFAULT_RELOAD_SECS: u64 = 10;
+
+// ── policy document ────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ScoringPolicy {
+    pub version: String,
+    pub platforms: BTreeMap<Platform, PlatformPolicy>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PlatformPolicy {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub caps: Vec<Cap>,
+    #[serde(default = "one")]
+    pub max: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Rule {
+    pub id: String,
+    pub signal: ReasonCode,
+    #[serde(default)]
+    pub requires: Vec<ReasonCode>,
+    #[serde(default)]
+    pub unless: Vec<ReasonCode>,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Cap {
+    pub id: String,
+    pub signal: ReasonCode,
+    pub max: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl ScoringPolicy {
+    pub fn builtin() -> Self {
+        Self::parse(DEFAULT_POLICY_JSON).expect("built-in policy/default.json is valid")
+    }
+
+    /// Parse and validate a policy document.
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let policy: ScoringPolicy = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Every platform must be covered and every number must be a sane
+    /// fraction, so a typo cannot silently hand out 10.0 trust.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version.trim().is_empty() {
+            return Err("policy version must not be blank".to_string());
+        }
+        for platform in [Platform::Web, Platform::Ios, Platform::Android] {
+            let Some(pp) = self.platforms.get(&platform) else {
+                return Err(format!("policy has no rules for platform `{platform}`"));
+            };
+            if !(0.0..=1.0).contains(&pp.max) {
+                return Err(format!("{platform}: max must be within [0, 1]"));
+            }
+            for rule in &pp.rules {
+                if !rule.weight.is_finite() || !(-1.0..=1.0).contains(&rule.weight) {
+                    return Err(format!("{platform}: rule `{}` weight must be within [-1, 1]", rule.id));
+                }
+            }
+            for cap in &pp.caps {
+                if !(0.0..=1.0).contains(&cap.max) {
+                    return Err(format!("{platform}: cap `{}` max must be within [0, 1]", cap.id));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Score `codes` reported by the `platform` verifier.
+    pub fn evaluate(&self, platform: Platform, codes: &[ReasonCode]) -> Verdict {
+        let mut signals: Vec<Signal> = Vec::with_capacity(codes.len());
+        for &code in codes {
+            if !signals.iter().any(|s| s.code == code) {
+                signals.push(Signal { code, weight: 0.0 });
+            }
+        }
+
+        let mut score = 0.0_f32;
+        // validate() guarantees coverage; an unknown platform scores zero.
+        let mut ceiling = 0.0_f32;
+        if let Some(pp) = self.platforms.get(&platform) {
+            for rule in &pp.rules {
+                let fires = codes.contains(&rule.signal)
+                    && rule.requires.iter().all(|c| codes.contains(c))
+                    && !rule.unless.iter().any(|c| codes.contains(c));
+                if fires {
+                    score += rule.weight;
+                    if let Some(signal) = signals.iter_mut().find(|s| s.code == rule.signal) {
+                        signal.weight += rule.weight;
+                    }
+                }
+            }
+            ceiling = pp.max;
+            for cap in &pp.caps {
+                if codes.contains(&cap.signal) {
+                    ceiling = ceiling.min(cap.max);
+                }
+            }
+        }
+
+        let trust_score = score.clamp(0.0, ceiling.max(0.0));
+        Verdict {
+            id: String::new(),
+            trust_score,
+            assurance_level: assurance_for(trust_score, &signals),
+            signals,
+            policy_version: self.version.clone(),
+        }
+    }
+}
+
+// ── hot-reloadable handle ──────────────────────────────────────────────
+
+/// Shared, swappable active policy.
+pub struct PolicyStore {
+    current: RwLock<Arc<ScoringPolicy>>,
+    path: Option<PathBuf>,
+    loaded_mtime: Mutex<Option<SystemTime>>,
+}
+
+impl PolicyStore {
+    pub fn new(policy: ScoringPolicy) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(policy)),
+            path: None,
+            loaded_mtime: Mutex::new(None),
+        }
+    }
+
+    /// Load from `TRUST_POLICY_PATH`, falling back to the built-in policy.
+    /// A configured but unreadable/invalid file is a startup error.
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("TRUST_POLICY_PATH") {
+            Ok(path) => {
+                let path = PathBuf::from(path);
+                let (policy, mtime) = read_policy_file(&path)?;
+                Ok(Self {
+                    current: RwLock::new(Arc::new(policy)),
+                    path: Some(path),
+                    loaded_mtime: Mutex::new(mtime),
+                })
+            }
+            Err(_) => Ok(Self::new(ScoringPolicy::builtin())),
+        }
+    }
+
+    pub fn current(&self) -> Arc<ScoringPolicy> {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Re-read the policy file if its modification time changed.  An invalid
+    /// file is reported and the previous policy stays active.
+    pub fn reload_if_changed(&self) -> Result<Option<String>, String> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+        let mtime = fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut loaded = self.loaded_mtime.lock().unwrap_or_else(|e| e.into_inner());
+        if mtime.is_some() && mtime == *loaded {
+            return Ok(None);
+        }
+        self.activate(path, &mut loaded).map(Some)
+    }
+
+    /// Re-read the policy file now, changed or not.  `Ok(None)` when running
+    /// the built-in policy.
+    pub fn reload(&self) -> Result<Option<String>, String> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+        let mut loaded = self.loaded_mtime.lock().unwrap_or_else(|e| e.into_inner());
+        self.activate(path, &mut loaded).map(Some)
+    }
+
+    fn activate(&self, path: &PathBuf, loaded: &mut Option<SystemTime>) -> Result<String, String> {
+        let (policy, mtime) = read_policy_file(path)?;
+        *loaded = mtime;
+        let version = policy.version.clone();
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(policy);
+        Ok(version)
+    }
+
+    /// Poll the policy file in the background.  No-op without a file.
+    pub fn spawn_watcher(self: &Arc<Self>) {
+        if self.path.is_none() {
+            return;
+        }
+        let secs = env::var("TRUST_POLICY_RELOAD_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|&s| s > 0)
+            .unwrap_or(DEFAULT_RELOAD_SECS);
+        let store = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(secs));
+            loop {
+                ticker.tick().await;
+                match store.reload_if_changed() {
+                    Ok(Some(version)) => {
+                        tracing::info!("trust policy reloaded: version {version}");
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        tracing::warn!("trust policy reload failed, keeping previous: {err}");
+                    }
+                }
+            }
+        });
+    }
+}
+
+fn read_policy_file(path: &PathBuf) -> Result<(ScoringPolicy, Option<SystemTime>), String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let policy =
+        ScoringPolicy::parse(&raw).map_err(|e| format!("invalid {}: {e}", path.display()))?;
+    let mtime = fs::metadata(path).and_then(|m| m.modified()).ok();
+    Ok((policy, mtime))
+}
+
+// ── dry run ────────────────────────────────────────────────────────────
+
+/// Number of buckets in dry-run score histograms (width 0.1).
+pub const HISTOGRAM_BUCKETS: usize = 10;
+
+/// Bucket index for a score in `[0, 1]`; 1.0 lands in the last bucket.
+pub fn bucket(score: f32) -> usize {
+    ((score.clamp(0.0, 1.0) * HISTOGRAM_BUCKETS as f32) as usize).min(HISTOGRAM_BUCKETS - 1)
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verdict::AssuranceLevel;
+
+    fn policy_with(web: PlatformPolicy) -> ScoringPolicy {
+        let mut policy = ScoringPolicy::builtin();
+        policy.version = "test".to_string();
+        policy.platforms.insert(Platform::Web, web);
+        policy
+    }
+
+    #[test]
+    fn builtin_policy_matches_stub_scores() {
+        let policy = ScoringPolicy::builtin();
+        let cases: &[(Platform, &[ReasonCode], f32)] = &[
+            (Platform::Web, &[ReasonCode::MockAttestation], 1.0),
+            (Platform::Web, &[ReasonCode::WebTokenPresent], 0.8),
+            (Platform::Web, &[ReasonCode::WebTokenTooShort], 0.0),
+            (Platform::Ios, &[ReasonCode::HwBackedKey, ReasonCode::AppIntegrityOk], 1.0),
+            (Platform::Android, &[ReasonCode::AttestationUnverified], 0.5),
+            (
+                Platform::Android,
+                &[ReasonCode::HwBackedKey, ReasonCode::AppIntegrityOk, ReasonCode::EmulatorSuspected],
+                0.5,
+            ),
+        ];
+        for (platform, codes, expected) in cases {
+            let v = policy.evaluate(*platform, codes);
+            assert!(
+                (v.trust_score - expected).abs() < 1e-6,
+                "{platform} {codes:?}: {} != {expected}",
+                v.trust_score
+            );
+            assert_eq!(v.policy_version, "stub-2026.1");
+        }
+    }
+
+    #[test]
+    fn signal_weights_attribute_rule_contributions() {
+        let v = ScoringPolicy::builtin().evaluate(
+            Platform::Ios,
+            &[ReasonCode::HwBackedKey, ReasonCode::AppIntegrityOk, ReasonCode::EmulatorSuspected],
+        );
+        let weights: Vec<(ReasonCode, f32)> = v.signals.iter().map(|s| (s.code, s.weight)).collect();
+        assert_eq!(
+            weights,
+            [
+                (ReasonCode::HwBackedKey, 0.5),
+                (ReasonCode::AppIntegrityOk, 0.5),
+                (ReasonCode::EmulatorSuspected, -0.5)
+            ]
+        );
+        assert_eq!(v.assurance_level, AssuranceLevel::Bronze);
+    }
+
+    #[test]
+    fn conditions_and_caps_apply() {
+        let policy = policy_with(PlatformPolicy {
+            rules: vec![Rule {
+                id: "token-with-integrity".to_string(),
+                signal: ReasonCode::WebTokenPresent,
+                requires: vec![ReasonCode::AppIntegrityOk],
+                unless: vec![ReasonCode::EmulatorSuspected],
+                weight: 0.9,
+            }],
+            caps: vec![Cap {
+                id: "no-hw".to_string(),
+                signal: ReasonCode::AttestationUnverified,
+                max: 0.6,
+            }],
+            max: 1.0,
+        });
+
+        let v = policy.evaluate(Platform::Web, &[ReasonCode::WebTokenPresent]);
+        assert!(v.trust_score.abs() < f32::EPSILON, "requires not met");
+
+        let v = policy.evaluate(
+            Platform::Web,
+            &[ReasonCode::WebTokenPresent, ReasonCode::AppIntegrityOk],
+        );
+        assert!((v.trust_score - 0.9).abs() < 1e-6);
+
+        let v = policy.evaluate(
+            Platform::Web,
+            &[
+                ReasonCode::WebTokenPresent,
+                ReasonCode::AppIntegrityOk,
+                ReasonCode::AttestationUnverified,
+            ],
+        );
+        assert!((v.trust_score - 0.6).abs() < 1e-6, "capped");
+
+        let v = policy.evaluate(
+            Platform::Web,
+            &[
+                ReasonCode::WebTokenPresent,
+                ReasonCode::AppIntegrityOk,
+                ReasonCode::EmulatorSuspected,
+            ],
+        );
+        assert!(v.trust_score.abs() < f32::EPSILON, "unless blocks");
+    }
+
+    #[test]
+    fn validation_rejects_bad_documents() {
+        assert!(ScoringPolicy::parse(r#"{"version":"x","platforms":{}}"#)
+            .unwrap_err()
+            .contains("no rules for platform"));
+
+        let mut policy = ScoringPolicy::builtin();
+        policy.platforms.get_mut(&Platform::Ios).unwrap().rules[0].weight = 10.0;
+        assert!(policy.validate().is_err());
+
+        let mut policy = ScoringPolicy::builtin();
+        policy.version = " ".to_string();
+        assert!(policy.validate().is_err());
+
+        assert!(ScoringPolicy::parse(r#"{"version":"x","platforms":{},"extra":1}"#).is_err());
+    }
+
+    #[test]
+    fn reloads_file_on_change() {
+        let dir = env::temp_dir().join(format!("vh-policy-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("policy.json");
+        fs::write(&path, DEFAULT_POLICY_JSON).unwrap();
+
+        let (policy, mtime) = read_policy_file(&path).unwrap();
+        let store = PolicyStore {
+            current: RwLock::new(Arc::new(policy)),
+            path: Some(path.clone()),
+            loaded_mtime: Mutex::new(mtime),
+        };
+        assert_eq!(store.reload_if_changed().unwrap(), None);
+
+        let updated = DEFAULT_POLICY_JSON.replace("stub-2026.1", "stub-2026.2");
+        fs::write(&path, updated).unwrap();
+        *store.loaded_mtime.lock().unwrap() = None;
+        assert_eq!(store.reload_if_changed().unwrap().as_deref(), Some("stub-2026.2"));
+        assert_eq!(store.current().version, "stub-2026.2");
+
+        fs::write(&path, "{").unwrap();
+        *store.loaded_mtime.lock().unwrap() = None;
+        assert!(store.reload_if_changed().is_err());
+        assert_eq!(store.current().version, "stub-2026.2", "previous policy kept");
+
+        // A forced reload ignores the modification time.
+        fs::write(&path, DEFAULT_POLICY_JSON).unwrap();
+        assert_eq!(store.reload().unwrap().as_deref(), Some("stub-2026.1"));
+        assert_eq!(store.reload_if_changed().unwrap(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        assert_eq!(bucket(0.0), 0);
+        assert_eq!(bucket(0.55), 5);
+        assert_eq!(bucket(1.0), HISTOGRAM_BUCKETS - 1);
+        assert_eq!(bucket(-3.0), 0);
+    }
+}
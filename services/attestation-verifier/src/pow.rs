@@ -0,0 +1,546 @@
+//! Adaptive hashcash proof of work for web attestation.
+//!
+//! Web integrity tokens are the easiest to farm, so when `POW_ENABLED` is
+//! set the verifier can make web clients pay CPU for each session.
+//! `GET /challenge` issues a nonce bound into a MAC'd challenge string with
+//! a difficulty in leading zero bits; the client finds a `solution` such
+//! that `SHA-256(challenge ‖ ":" ‖ solution)` has that many leading zero
+//! bits and sends `{ challenge, solution }` as `pow` in its `/verify`
+//! payload, using the challenge's nonce as the attestation `nonce`.
+//!
+//! Difficulty is `POW_BASE_DIFFICULTY` (default 0, i.e. free while calm)
+//! plus pressure from the client's IP and its subnet (/24, /64):
+//!
+//! - velocity: more than `POW_VELOCITY_PER_MIN` (default 20; ×8 for the
+//!   subnet) `/verify` attempts in the last minute adds 8 bits, plus 4 per
+//!   further doubling;
+//! - failures: more than half of at least 5 recent attempts failing adds 8.
+//!
+//! The total is capped at `POW_MAX_DIFFICULTY` (default 22).  Challenges
+//! live `POW_CHALLENGE_TTL_SECS` (default 120), are single-use, and must be
+//! at least as hard as the current requirement when redeemed.
+
+use std::collections::HashMap;
+use std::env;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::ratelimit::canonical;
+
+/// Hardest difficulty that can be configured.
+pub const MAX_DIFFICULTY_BITS: u8 = 32;
+
+/// Velocity window.
+const WINDOW_MS: u64 = 60_000;
+
+/// Attempts needed before a failure rate counts.
+const MIN_FAILURE_SAMPLE: u32 = 5;
+
+/// Subnet velocity threshold relative to the per-IP one.
+const SUBNET_FACTOR: u32 = 8;
+
+/// Windows tracked before stale ones are swept.
+const MAX_TRACKED_WINDOWS: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowConfig {
+    pub base_difficulty: u8,
+    pub max_difficulty: u8,
+    pub velocity_per_min: u32,
+    pub challenge_ttl_ms: u64,
+}
+
+impl Default for PowConfig {
+    fn default() -> Self {
+        Self {
+            base_difficulty: 0,
+            max_difficulty: 22,
+            velocity_per_min: 20,
+            challenge_ttl_ms: 120_000,
+        }
+    }
+}
+
+/// Issued by `GET /challenge`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Challenge {
+    /// Use as the attestation payload's `nonce`.
+    pub nonce: String,
+    /// Leading zero bits required; 0 means no work is needed.
+    pub difficulty: u8,
+    /// Epoch milliseconds.
+    pub expires_at: u64,
+    /// Opaque string to hash; echo it back in `pow.challenge`.
+    pub challenge: String,
+}
+
+/// `pow` field of a `/verify` payload.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PowSolution {
+    pub challenge: String,
+    pub solution: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowError {
+    Required,
+    Invalid,
+    Expired,
+    NonceMismatch,
+    Insufficient,
+    Replayed,
+}
+
+impl PowError {
+    pub fn code(self) -> &'static str {
+        match self {
+            PowError::Required => "POW_REQUIRED",
+            PowError::Invalid => "POW_INVALID",
+            PowError::Expired => "POW_EXPIRED",
+            PowError::NonceMismatch => "POW_NONCE_MISMATCH",
+            PowError::Insufficient => "POW_INSUFFICIENT",
+            PowError::Replayed => "POW_REPLAYED",
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            PowError::Required => "proof of work required; request a challenge from /challenge",
+            PowError::Invalid => "proof of work challenge or solution is not valid",
+            PowError::Expired => "proof of work challenge has expired",
+            PowError::NonceMismatch => "payload nonce does not match the challenge",
+            PowError::Insufficient => "difficulty has risen; request a new challenge",
+            PowError::Replayed => "proof of work challenge was already used",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Window {
+    started_ms: u64,
+    requests: u32,
+    failures: u32,
+    prev_requests: u32,
+    prev_failures: u32,
+}
+
+impl Window {
+    fn roll(&mut self, now_ms: u64) {
+        let elapsed = now_ms.saturating_sub(self.started_ms);
+        if elapsed < WINDOW_MS {
+            return;
+        }
+        if elapsed < 2 * WINDOW_MS {
+            self.prev_requests = self.requests;
+            self.prev_failures = self.failures;
+        } else {
+            self.prev_requests = 0;
+            self.prev_failures = 0;
+        }
+        self.requests = 0;
+        self.failures = 0;
+        self.started_ms = now_ms;
+    }
+
+    /// Attempts and failures over the trailing minute, with the previous
+    /// window weighted by how much of it still overlaps.
+    fn estimate(&self, now_ms: u64) -> (u32, u32) {
+        let mut window = *self;
+        window.roll(now_ms);
+        let elapsed = now_ms.saturating_sub(window.started_ms).min(WINDOW_MS);
+        let overlap = |prev: u32| (u64::from(prev) * (WINDOW_MS - elapsed) / WINDOW_MS) as u32;
+        (
+            window.requests + overlap(window.prev_requests),
+            window.failures + overlap(window.prev_failures),
+        )
+    }
+}
+
+pub struct PowGuard {
+    config: Option<PowConfig>,
+    key: [u8; 32],
+    windows: Mutex<HashMap<String, Window>>,
+    /// Redeemed challenge nonces and when they expire.
+    spent: Mutex<HashMap<String, u64>>,
+}
+
+impl PowGuard {
+    /// `None` disables proof of work entirely.
+    pub fn new(config: Option<PowConfig>) -> Self {
+        Self {
+            config,
+            key: rand::random(),
+            windows: Mutex::new(HashMap::new()),
+            spent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        let enabled = matches!(
+            env::var("POW_ENABLED").as_deref().map(str::trim),
+            Ok("1" | "true" | "TRUE" | "yes")
+        );
+        if !enabled {
+            return Ok(Self::new(None));
+        }
+        let mut config = PowConfig::default();
+        if let Some(bits) = env_number::<u8>("POW_BASE_DIFFICULTY")? {
+            config.base_difficulty = bits;
+        }
+        if let Some(bits) = env_number::<u8>("POW_MAX_DIFFICULTY")? {
+            config.max_difficulty = bits;
+        }
+        if let Some(rate) = env_number::<u32>("POW_VELOCITY_PER_MIN")? {
+            config.velocity_per_min = rate.max(1);
+        }
+        if let Some(secs) = env_number::<u64>("POW_CHALLENGE_TTL_SECS")? {
+            config.challenge_ttl_ms = secs * 1000;
+        }
+        if config.max_difficulty > MAX_DIFFICULTY_BITS
+            || config.base_difficulty > config.max_difficulty
+        {
+            return Err(format!(
+                "POW difficulties must satisfy base <= max <= {MAX_DIFFICULTY_BITS}"
+            ));
+        }
+        Ok(Self::new(Some(config)))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Bits currently required from `ip`; 0 when disabled or calm.
+    pub fn difficulty_for(&self, ip: Option<IpAddr>, now_ms: u64) -> u8 {
+        let Some(config) = self.config else {
+            return 0;
+        };
+        let pressure = match ip {
+            Some(ip) => {
+                let windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+                let bits = |key: &str, threshold: u32| {
+                    windows
+                        .get(key)
+                        .map_or(0, |w| pressure_bits(w.estimate(now_ms), threshold))
+                };
+                bits(&ip_key(ip), config.velocity_per_min).max(bits(
+                    &subnet_key(ip),
+                    config.velocity_per_min.saturating_mul(SUBNET_FACTOR),
+                ))
+            }
+            None => 0,
+        };
+        (u32::from(config.base_difficulty) + pressure).min(u32::from(config.max_difficulty)) as u8
+    }
+
+    /// A fresh challenge at the difficulty currently required from `ip`.
+    pub fn issue(&self, ip: Option<IpAddr>, now_ms: u64) -> Challenge {
+        let ttl = self.config.unwrap_or_default().challenge_ttl_ms;
+        let nonce = hex::encode(rand::random::<[u8; 16]>());
+        let difficulty = self.difficulty_for(ip, now_ms);
+        let expires_at = now_ms + ttl;
+        let body = format!("v1.{nonce}.{difficulty}.{expires_at}");
+        let challenge = format!("{body}.{}", hex::encode(self.mac(&body)));
+        Challenge {
+            nonce,
+            difficulty,
+            expires_at,
+            challenge,
+        }
+    }
+
+    /// Check a web client's proof of work before its token is verified.
+    pub fn check(
+        &self,
+        ip: Option<IpAddr>,
+        pow: Option<&PowSolution>,
+        nonce: &str,
+        now_ms: u64,
+    ) -> Result<(), PowError> {
+        if self.config.is_none() {
+            return Ok(());
+        }
+        let required = self.difficulty_for(ip, now_ms);
+        let Some(pow) = pow else {
+            return if required == 0 { Ok(()) } else { Err(PowError::Required) };
+        };
+
+        let (body, mac) = pow.challenge.rsplit_once('.').ok_or(PowError::Invalid)?;
+        let mac = hex::decode(mac).map_err(|_| PowError::Invalid)?;
+        let mut expected = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes any key length");
+        expected.update(body.as_bytes());
+        expected.verify_slice(&mac).map_err(|_| PowError::Invalid)?;
+        let parts: Vec<&str> = body.split('.').collect();
+        let [_, challenge_nonce, difficulty, expires_at] = parts[..] else {
+            return Err(PowError::Invalid);
+        };
+        let difficulty: u8 = difficulty.parse().map_err(|_| PowError::Invalid)?;
+        let expires_at: u64 = expires_at.parse().map_err(|_| PowError::Invalid)?;
+
+        if now_ms >= expires_at {
+            return Err(PowError::Expired);
+        }
+        if challenge_nonce != nonce {
+            return Err(PowError::NonceMismatch);
+        }
+        if difficulty < required {
+            return Err(PowError::Insufficient);
+        }
+        let digest = Sha256::digest(format!("{}:{}", pow.challenge, pow.solution));
+        if leading_zero_bits(&digest) < u32::from(difficulty) {
+            return Err(PowError::Invalid);
+        }
+
+        let mut spent = self.spent.lock().unwrap_or_else(|e| e.into_inner());
+        spent.retain(|_, expiry| *expiry > now_ms);
+        if spent.insert(challenge_nonce.to_string(), expires_at).is_some() {
+            return Err(PowError::Replayed);
+        }
+        Ok(())
+    }
+
+    /// Count one `/verify` attempt from `ip` toward its pressure.
+    pub fn record(&self, ip: IpAddr, failed: bool, now_ms: u64) {
+        if self.config.is_none() {
+            return;
+        }
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.len() >= MAX_TRACKED_WINDOWS {
+            windows.retain(|_, w| now_ms.saturating_sub(w.started_ms) < 2 * WINDOW_MS);
+        }
+        for key in [ip_key(ip), subnet_key(ip)] {
+            let window = windows.entry(key).or_insert(Window {
+                started_ms: now_ms,
+                ..Window::default()
+            });
+            window.roll(now_ms);
+            window.requests += 1;
+            window.failures += u32::from(failed);
+        }
+    }
+
+    fn mac(&self, body: &str) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes any key length");
+        mac.update(body.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+}
+
+fn env_number<T: std::str::FromStr>(name: &str) -> Result<Option<T>, String> {
+    match env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{name}: not a valid number: {value:?}")),
+        Err(_) => Ok(None),
+    }
+}
+
+fn pressure_bits((requests, failures): (u32, u32), threshold: u32) -> u32 {
+    let mut bits = 0;
+    if requests > threshold {
+        bits += 8 + 4 * (requests / threshold).ilog2();
+    }
+    if requests >= MIN_FAILURE_SAMPLE && failures * 2 > requests {
+        bits += 8;
+    }
+    bits
+}
+
+fn ip_key(ip: IpAddr) -> String {
+    format!("ip/{}", canonical(ip))
+}
+
+/// The /24 (IPv4) or /64 (IPv6) containing `ip`.
+fn subnet_key(ip: IpAddr) -> String {
+    match canonical(ip) {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            format!("net/{a}.{b}.{c}.0/24")
+        }
+        IpAddr::V6(v6) => {
+            let s = v6.segments();
+            format!("net/{:x}:{:x}:{:x}:{:x}::/64", s[0], s[1], s[2], s[3])
+        }
+    }
+}
+
+fn leading_zero_bits(digest: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in digest {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// Brute-force a solution, as a client would.
+#[cfg(test)]
+pub fn solve(challenge: &Challenge) -> PowSolution {
+    (0u64..)
+        .map(|counter| PowSolution {
+            challenge: challenge.challenge.clone(),
+            solution: counter.to_string(),
+        })
+        .find(|pow| {
+            let digest = Sha256::digest(format!("{}:{}", pow.challenge, pow.solution));
+            leading_zero_bits(&digest) >= u32::from(challenge.difficulty)
+        })
+        .unwrap()
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> Option<IpAddr> {
+        Some(value.parse().unwrap())
+    }
+
+    fn guard(base_difficulty: u8) -> PowGuard {
+        PowGuard::new(Some(PowConfig {
+            base_difficulty,
+            ..PowConfig::default()
+        }))
+    }
+
+    #[test]
+    fn disabled_guard_requires_nothing() {
+        let guard = PowGuard::new(None);
+        for _ in 0..100 {
+            guard.record(ip("203.0.113.9").unwrap(), true, 0);
+        }
+        assert_eq!(guard.difficulty_for(ip("203.0.113.9"), 0), 0);
+        assert_eq!(guard.check(ip("203.0.113.9"), None, "00", 0), Ok(()));
+    }
+
+    #[test]
+    fn solved_challenge_is_accepted_once() {
+        let guard = guard(8);
+        let challenge = guard.issue(ip("203.0.113.9"), 0);
+        assert_eq!(challenge.difficulty, 8);
+        assert_eq!(challenge.expires_at, 120_000);
+        assert_eq!(
+            guard.check(ip("203.0.113.9"), None, &challenge.nonce, 0),
+            Err(PowError::Required)
+        );
+
+        let pow = solve(&challenge);
+        assert_eq!(guard.check(ip("203.0.113.9"), Some(&pow), &challenge.nonce, 1_000), Ok(()));
+        assert_eq!(
+            guard.check(ip("203.0.113.9"), Some(&pow), &challenge.nonce, 1_000),
+            Err(PowError::Replayed)
+        );
+    }
+
+    #[test]
+    fn bad_solutions_are_rejected() {
+        let guard = guard(8);
+        let challenge = guard.issue(None, 0);
+        let pow = solve(&challenge);
+
+        assert_eq!(guard.check(None, Some(&pow), "ffff", 0), Err(PowError::NonceMismatch));
+        assert_eq!(
+            guard.check(None, Some(&pow), &challenge.nonce, 120_000),
+            Err(PowError::Expired)
+        );
+
+        // Lowering the difficulty in the challenge breaks its MAC.
+        let forged = PowSolution {
+            challenge: pow.challenge.replacen(".8.", ".0.", 1),
+            solution: pow.solution.clone(),
+        };
+        assert_eq!(guard.check(None, Some(&forged), &challenge.nonce, 0), Err(PowError::Invalid));
+
+        let unsolved = (0u64..)
+            .map(|n| PowSolution {
+                challenge: challenge.challenge.clone(),
+                solution: n.to_string(),
+            })
+            .find(|p| leading_zero_bits(&Sha256::digest(format!("{}:{}", p.challenge, p.solution))) < 8)
+            .unwrap();
+        assert_eq!(guard.check(None, Some(&unsolved), &challenge.nonce, 0), Err(PowError::Invalid));
+
+        // Challenges from another verifier instance are not accepted.
+        let other = PowGuard::new(Some(PowConfig::default())).issue(None, 0);
+        assert_eq!(
+            guard.check(None, Some(&solve(&other)), &other.nonce, 0),
+            Err(PowError::Invalid)
+        );
+    }
+
+    #[test]
+    fn difficulty_rises_with_velocity() {
+        let guard = guard(0);
+        let client = ip("203.0.113.9");
+        for _ in 0..20 {
+            guard.record(client.unwrap(), false, 0);
+        }
+        assert_eq!(guard.difficulty_for(client, 0), 0);
+        guard.record(client.unwrap(), false, 0);
+        assert_eq!(guard.difficulty_for(client, 0), 8);
+        for _ in 0..20 {
+            guard.record(client.unwrap(), false, 0);
+        }
+        assert_eq!(guard.difficulty_for(client, 0), 12);
+
+        // Neighbours feel the subnet only once it is busy in aggregate.
+        assert_eq!(guard.difficulty_for(ip("203.0.113.10"), 0), 0);
+        // Pressure decays as the window slides.
+        assert_eq!(guard.difficulty_for(client, 2 * WINDOW_MS), 0);
+    }
+
+    #[test]
+    fn difficulty_rises_with_failure_rate() {
+        let guard = guard(0);
+        let client = ip("2001:db8::1");
+        for failed in [true, true, true, false] {
+            guard.record(client.unwrap(), failed, 0);
+        }
+        assert_eq!(guard.difficulty_for(client, 0), 0);
+        guard.record(client.unwrap(), true, 0);
+        assert_eq!(guard.difficulty_for(client, 0), 8);
+        // Same /64.
+        assert_eq!(guard.difficulty_for(ip("2001:db8::2"), 0), 8);
+    }
+
+    #[test]
+    fn stale_challenges_fall_short_after_a_spike() {
+        let guard = guard(0);
+        let client = ip("203.0.113.9");
+        let calm = guard.issue(client, 0);
+        assert_eq!(calm.difficulty, 0);
+        for _ in 0..21 {
+            guard.record(client.unwrap(), false, 0);
+        }
+        assert_eq!(
+            guard.check(client, Some(&solve(&calm)), &calm.nonce, 0),
+            Err(PowError::Insufficient)
+        );
+        assert!(guard.check(client, None, &calm.nonce, 0).is_err());
+    }
+
+    #[test]
+    fn difficulty_is_capped() {
+        let guard = PowGuard::new(Some(PowConfig {
+            max_difficulty: 10,
+            ..PowConfig::default()
+        }));
+        for _ in 0..1000 {
+            guard.record(ip("203.0.113.9").unwrap(), true, 0);
+        }
+        assert_eq!(guard.difficulty_for(ip("203.0.113.9"), 0), 10);
+    }
+}
@@ -0,0 +1,418 @@
+//! Token-bucket rate limiting.
+//!
+//! Buckets are keyed by client IP (every API route except `/health`, once
+//! the route has matched, so unknown paths stay 404s), and on `/verify`
+//! additionally by `device_key` and by the resulting nullifier.
+//! Each dimension is configured as `<count>/<sec|min|hour>`: the bucket
+//! holds `count` tokens and refills at `count` per period.  `off` disables
+//! a dimension.
+//!
+//! | variable                      | default    |
+//! |-------------------------------|------------|
+//! | `RATE_LIMIT_IP`               | `300/min`  |
+//! | `RATE_LIMIT_DEVICE_KEY`       | `10/min`   |
+//! | `RATE_LIMIT_NULLIFIER`        | `10/min`   |
+//! | `RATE_LIMIT_TRUSTED_PROXIES`  | (none)     |
+//!
+//! `X-Forwarded-For` is only honored when the peer is a trusted proxy
+//! (comma-separated IPs or CIDRs, e.g. Traefik's network).  The client is
+//! then the right-most address in the chain that is not itself trusted.
+//!
+//! Operators can switch limiting off and on at runtime through the admin
+//! API; the configured limits and bucket levels are kept while it is off.
+
+use std::collections::HashMap;
+use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Most buckets tracked at once.  Reaching it sweeps full (idle) buckets,
+/// then the least recently used, down to `SWEEP_TARGET`.
+const MAX_TRACKED_BUCKETS: usize = 100_000;
+
+/// Size after a sweep; the slack keeps sweeps rare.
+const SWEEP_TARGET: usize = MAX_TRACKED_BUCKETS * 9 / 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dimension {
+    Ip,
+    DeviceKey,
+    Nullifier,
+}
+
+impl Dimension {
+    pub fn name(self) -> &'static str {
+        match self {
+            Dimension::Ip => "ip",
+            Dimension::DeviceKey => "device_key",
+            Dimension::Nullifier => "nullifier",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    /// Bucket capacity, i.e. the largest burst.
+    pub capacity: u32,
+    /// Tokens added per second.
+    pub refill_per_sec: f64,
+}
+
+impl Limit {
+    /// `<count>/<sec|min|hour>`; `off` yields `None`.
+    pub fn parse(value: &str) -> Result<Option<Self>, String> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("off") {
+            return Ok(None);
+        }
+        let invalid = || format!("expected <count>/<sec|min|hour> or off, got {value:?}");
+        let (count, period) = value.split_once('/').ok_or_else(invalid)?;
+        let capacity: u32 = count.trim().parse().map_err(|_| invalid())?;
+        let period_secs = match period.trim() {
+            "s" | "sec" | "second" => 1.0,
+            "m" | "min" | "minute" => 60.0,
+            "h" | "hour" => 3600.0,
+            _ => return Err(invalid()),
+        };
+        if capacity == 0 {
+            return Err(invalid());
+        }
+        Ok(Some(Limit {
+            capacity,
+            refill_per_sec: f64::from(capacity) / period_secs,
+        }))
+    }
+}
+
+/// IPv4 or IPv6 network, or a single address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?, Some(prefix.parse().ok()?)),
+            None => (value.parse::<IpAddr>().ok()?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Cidr { addr, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix)).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(self.prefix)).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// IPv4-mapped IPv6 addresses (dual-stack sockets) as plain IPv4.
+pub fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        v4 => v4,
+    }
+}
+
+/// The request was refused; retry after this many seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exceeded {
+    pub dimension: Dimension,
+    pub retry_after_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated_ms: u64,
+}
+
+pub struct RateLimiter {
+    limits: HashMap<Dimension, Limit>,
+    trusted_proxies: Vec<Cidr>,
+    buckets: Mutex<HashMap<(Dimension, String), Bucket>>,
+    enabled: AtomicBool,
+}
+
+impl RateLimiter {
+    pub fn new(limits: HashMap<Dimension, Limit>, trusted_proxies: Vec<Cidr>) -> Self {
+        Self {
+            limits,
+            trusted_proxies,
+            buckets: Mutex::new(HashMap::new()),
+            enabled: AtomicBool::new(true),
+        }
+    }
+
+    /// No limits; every request is allowed.
+    pub fn unlimited() -> Self {
+        Self::new(HashMap::new(), Vec::new())
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        let mut limits = HashMap::new();
+        for (dimension, var, default) in [
+            (Dimension::Ip, "RATE_LIMIT_IP", "300/min"),
+            (Dimension::DeviceKey, "RATE_LIMIT_DEVICE_KEY", "10/min"),
+            (Dimension::Nullifier, "RATE_LIMIT_NULLIFIER", "10/min"),
+        ] {
+            let value = env::var(var).unwrap_or_else(|_| default.to_string());
+            if let Some(limit) = Limit::parse(&value).map_err(|e| format!("{var}: {e}"))? {
+                limits.insert(dimension, limit);
+            }
+        }
+        let trusted_proxies = env::var("RATE_LIMIT_TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| {
+                Cidr::parse(entry)
+                    .ok_or_else(|| format!("RATE_LIMIT_TRUSTED_PROXIES: invalid entry {entry:?}"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(limits, trusted_proxies))
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|net| net.contains(ip))
+    }
+
+    /// The client address: the peer itself, or — when the peer is a
+    /// trusted proxy — the right-most untrusted `X-Forwarded-For` hop.
+    pub fn client_ip(&self, peer: Option<SocketAddr>, forwarded_for: Option<&str>) -> Option<IpAddr> {
+        let peer = canonical(peer?.ip());
+        if !self.is_trusted(peer) {
+            return Some(peer);
+        }
+        let mut client = peer;
+        for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+            match hop.trim().parse::<IpAddr>() {
+                Ok(ip) => {
+                    client = canonical(ip);
+                    if !self.is_trusted(client) {
+                        break;
+                    }
+                }
+                // An unparseable hop ends the trustworthy part of the chain.
+                Err(_) => break,
+            }
+        }
+        Some(client)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Switch limiting on or off; returns the previous setting.
+    pub fn set_enabled(&self, enabled: bool) -> bool {
+        self.enabled.swap(enabled, Ordering::SeqCst)
+    }
+
+    /// Whole tokens left in `key`'s bucket, or `None` when `dimension` is
+    /// not limited.  Takes nothing.
+    pub fn remaining(&self, dimension: Dimension, key: &str, now_ms: u64) -> Option<u32> {
+        let limit = self.limits.get(&dimension)?;
+        let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        Some(match buckets.get(&(dimension, key.to_string())) {
+            Some(bucket) => refilled(bucket, limit, now_ms).floor() as u32,
+            None => limit.capacity,
+        })
+    }
+
+    /// Take one token from `key`'s bucket in `dimension`.
+    pub fn check(&self, dimension: Dimension, key: &str, now_ms: u64) -> Result<(), Exceeded> {
+        let Some(limit) = self.limits.get(&dimension).filter(|_| self.is_enabled()) else {
+            return Ok(());
+        };
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_TRACKED_BUCKETS {
+            sweep(&mut buckets, &self.limits, now_ms);
+        }
+        let bucket = buckets
+            .entry((dimension, key.to_string()))
+            .or_insert(Bucket {
+                tokens: f64::from(limit.capacity),
+                updated_ms: now_ms,
+            });
+        bucket.tokens = refilled(bucket, limit, now_ms);
+        bucket.updated_ms = now_ms.max(bucket.updated_ms);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let wait_secs = (1.0 - bucket.tokens) / limit.refill_per_sec;
+        Err(Exceeded {
+            dimension,
+            retry_after_secs: (wait_secs.ceil() as u64).max(1),
+        })
+    }
+}
+
+/// Drop full buckets — forgetting them changes nothing — and, if that is not
+/// enough, the least recently updated ones until `SWEEP_TARGET` is reached.
+/// Evicting a partly drained bucket refills it early, which only favors
+/// the quietest clients.
+fn sweep(
+    buckets: &mut HashMap<(Dimension, String), Bucket>,
+    limits: &HashMap<Dimension, Limit>,
+    now_ms: u64,
+) {
+    buckets.retain(|(dimension, _), bucket| match limits.get(dimension) {
+        Some(limit) => refilled(bucket, limit, now_ms) < f64::from(limit.capacity),
+        None => false,
+    });
+    if buckets.len() <= SWEEP_TARGET {
+        return;
+    }
+    // Exactly `excess` keys: under a flood many buckets share a millisecond,
+    // and dropping every tie would refill drained buckets wholesale.
+    let excess = buckets.len() - SWEEP_TARGET;
+    let mut oldest: Vec<(u64, (Dimension, String))> = buckets
+        .iter()
+        .map(|(key, bucket)| (bucket.updated_ms, key.clone()))
+        .collect();
+    oldest.select_nth_unstable(excess - 1);
+    for (_, key) in oldest.drain(..excess) {
+        buckets.remove(&key);
+    }
+}
+
+fn refilled(bucket: &Bucket, limit: &Limit, now_ms: u64) -> f64 {
+    let elapsed_secs = now_ms.saturating_sub(bucket.updated_ms) as f64 / 1000.0;
+    (bucket.tokens + elapsed_secs * limit.refill_per_sec).min(f64::from(limit.capacity))
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(limit: &str, proxies: &[&str]) -> RateLimiter {
+        RateLimiter::new(
+            HashMap::from([(Dimension::Ip, Limit::parse(limit).unwrap().unwrap())]),
+            proxies.iter().map(|p| Cidr::parse(p).unwrap()).collect(),
+        )
+    }
+
+    #[test]
+    fn parses_limits() {
+        assert_eq!(
+            Limit::parse("60/min"),
+            Ok(Some(Limit {
+                capacity: 60,
+                refill_per_sec: 1.0
+            }))
+        );
+        assert_eq!(Limit::parse("OFF"), Ok(None));
+        assert!(Limit::parse("0/min").is_err());
+        assert!(Limit::parse("10/day").is_err());
+        assert!(Limit::parse("ten/min").is_err());
+    }
+
+    #[test]
+    fn bucket_allows_a_burst_then_refills() {
+        let limiter = limiter("3/min", &[]);
+        for _ in 0..3 {
+            assert!(limiter.check(Dimension::Ip, "198.51.100.7", 0).is_ok());
+        }
+        assert_eq!(
+            limiter.check(Dimension::Ip, "198.51.100.7", 0),
+            Err(Exceeded {
+                dimension: Dimension::Ip,
+                retry_after_secs: 20
+            })
+        );
+        // Other keys and unconfigured dimensions are unaffected.
+        assert!(limiter.check(Dimension::Ip, "198.51.100.8", 0).is_ok());
+        assert!(limiter.check(Dimension::DeviceKey, "198.51.100.7", 0).is_ok());
+
+        assert!(limiter.check(Dimension::Ip, "198.51.100.7", 19_000).is_err());
+        assert!(limiter.check(Dimension::Ip, "198.51.100.7", 20_000).is_ok());
+        assert!(limiter.check(Dimension::Ip, "198.51.100.7", 20_000).is_err());
+    }
+
+    #[test]
+    fn tracked_buckets_stay_bounded_under_key_rotation() {
+        // Slow refill: no bucket is ever full again within the test.
+        let limiter = limiter("5/hour", &[]);
+        for i in 0..MAX_TRACKED_BUCKETS as u64 + 5_000 {
+            assert!(limiter.check(Dimension::Ip, &format!("2001:db8::{i:x}"), i).is_ok());
+        }
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.len() <= MAX_TRACKED_BUCKETS);
+        drop(buckets);
+        // The newest keys keep their state; the oldest were evicted.
+        let newest = format!("2001:db8::{:x}", MAX_TRACKED_BUCKETS as u64 + 4_999);
+        let now = MAX_TRACKED_BUCKETS as u64 + 5_000;
+        assert_eq!(limiter.remaining(Dimension::Ip, &newest, now), Some(4));
+        assert_eq!(limiter.remaining(Dimension::Ip, "2001:db8::0", now), Some(5));
+    }
+
+    #[test]
+    fn sweep_evicts_exactly_the_excess_when_timestamps_tie() {
+        let limiter = limiter("5/hour", &[]);
+        // A flood within one millisecond: every bucket shares `updated_ms`.
+        for i in 0..MAX_TRACKED_BUCKETS as u64 + 1 {
+            assert!(limiter.check(Dimension::Ip, &format!("2001:db8::{i:x}"), 7).is_ok());
+        }
+        let tracked = limiter.buckets.lock().unwrap().len();
+        assert_eq!(tracked, SWEEP_TARGET + 1);
+    }
+
+    #[test]
+    fn limiting_can_be_switched_off() {
+        let limiter = limiter("1/min", &[]);
+        assert_eq!(limiter.remaining(Dimension::Ip, "198.51.100.7", 0), Some(1));
+        assert!(limiter.check(Dimension::Ip, "198.51.100.7", 0).is_ok());
+        assert_eq!(limiter.remaining(Dimension::Ip, "198.51.100.7", 0), Some(0));
+        assert_eq!(limiter.remaining(Dimension::DeviceKey, "k", 0), None);
+
+        assert!(limiter.set_enabled(false));
+        assert!(limiter.check(Dimension::Ip, "198.51.100.7", 0).is_ok());
+        limiter.set_enabled(true);
+        assert!(limiter.check(Dimension::Ip, "198.51.100.7", 0).is_err());
+    }
+
+    #[test]
+    fn cidr_membership() {
+        let net = Cidr::parse("10.0.0.0/8").unwrap();
+        assert!(net.contains("10.1.2.3".parse().unwrap()));
+        assert!(net.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!net.contains("11.0.0.1".parse().unwrap()));
+        assert!(Cidr::parse("fd00::/8").unwrap().contains("fd12::1".parse().unwrap()));
+        assert!(Cidr::parse("0.0.0.0/0").unwrap().contains("8.8.8.8".parse().unwrap()));
+        assert_eq!(Cidr::parse("10.0.0.0/33"), None);
+        assert_eq!(Cidr::parse("example.com"), None);
+    }
+
+    #[test]
+    fn forwarded_for_is_only_trusted_from_proxies() {
+        let peer = |ip: &str| Some(SocketAddr::new(ip.parse().unwrap(), 40000));
+        let limiter = limiter("3/min", &["10.0.0.0/8"]);
+        let client = |p: &str, xff: Option<&str>| limiter.client_ip(peer(p), xff).unwrap().to_string();
+
+        // Direct clients cannot spoof their address.
+        assert_eq!(client("203.0.113.9", Some("1.2.3.4")), "203.0.113.9");
+        // Behind Traefik the right-most untrusted hop is the client.
+        assert_eq!(client("10.0.0.2", Some("1.2.3.4, 203.0.113.9")), "203.0.113.9");
+        assert_eq!(client("10.0.0.2", Some("203.0.113.9, 10.0.0.5")), "203.0.113.9");
+        assert_eq!(client("10.0.0.2", Some("garbage, 203.0.113.9")), "203.0.113.9");
+        assert_eq!(client("10.0.0.2", None), "10.0.0.2");
+        assert_eq!(limiter.client_ip(None, Some("1.2.3.4")), None);
+    }
+}
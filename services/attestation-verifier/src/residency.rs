@@ -0,0 +1,571 @@
+//! Residency-set Merkle root registry (identity spec §4.2).
+//!
+//! A constituency proof's `merkle_root` is only fresh if the residency-set
+//! authority published it within the freshness window (30 days by
+//! default).  Authorities announce roots as Ed25519-signed
+//! `RootAnnouncement`s, either dropped into `RESIDENCY_ROOTS_DIR` or posted
+//! to `/residency/roots`; the signature is what authorizes a publication,
+//! so only holders of a key listed in `RESIDENCY_AUTHORITY_KEYS` can add
+//! roots.  Accepted posts are written back to the directory so they
+//! survive a restart.
+
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::keys::fingerprint;
+#[cfg(test)]
+use crate::keys::KeyRing;
+use crate::merkle::TreeHash;
+
+/// Default freshness window: 30 days (spec §4.2 target state).
+pub const DEFAULT_MAX_ROOT_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Announcements may be stamped this far ahead of our clock.
+const CLOCK_SKEW_SECS: u64 = 5 * 60;
+
+const MAX_ROOT_LEN: usize = 256;
+
+/// Domain separator for announcement signatures.
+const ANNOUNCEMENT_CONTEXT: &str = "vh-residency-root/v1";
+
+/// A signed statement that `root` was published at `published_at`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RootAnnouncement {
+    pub root: String,
+    /// Unix seconds.
+    pub published_at: u64,
+    /// Key id of the signing authority.
+    pub kid: String,
+    /// Base64url Ed25519 signature over `signing_input()`.
+    pub signature: String,
+}
+
+#[cfg(test)]
+impl RootAnnouncement {
+    /// Sign an announcement with an authority key.
+    pub fn sign(keys: &KeyRing, root: &str, published_at: u64) -> Self {
+        let signature = keys.sign(signing_input(root, published_at).as_bytes());
+        Self {
+            root: root.to_string(),
+            published_at,
+            kid: keys.kid().to_string(),
+            signature: URL_SAFE_NO_PAD.encode(signature.to_bytes()),
+        }
+    }
+}
+
+fn signing_input(root: &str, published_at: u64) -> String {
+    format!("{ANNOUNCEMENT_CONTEXT}\n{root}\n{published_at}")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RootError {
+    InvalidRoot,
+    UnknownAuthority,
+    BadSignature,
+    FromFuture,
+}
+
+impl RootError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RootError::InvalidRoot => "INVALID_ROOT",
+            RootError::UnknownAuthority => "UNKNOWN_ROOT_AUTHORITY",
+            RootError::BadSignature => "INVALID_ROOT_SIGNATURE",
+            RootError::FromFuture => "ROOT_FROM_FUTURE",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            RootError::InvalidRoot => "root must be a non-blank string without whitespace",
+            RootError::UnknownAuthority => "announcement is not signed by a residency authority",
+            RootError::BadSignature => "announcement signature does not verify",
+            RootError::FromFuture => "announcement is timestamped in the future",
+        }
+    }
+}
+
+/// A registered root, as listed by `/residency/roots`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishedRoot {
+    pub root: String,
+    pub published_at: u64,
+    pub expires_at: u64,
+    pub kid: String,
+    pub fresh: bool,
+}
+
+/// Authority keys and the roots they signed; swapped as a whole on reload.
+#[derive(Default)]
+struct Anchors {
+    /// Authority verifying keys by kid.
+    authorities: HashMap<String, VerifyingKey>,
+    /// Root → latest accepted announcement.
+    roots: HashMap<String, RootAnnouncement>,
+}
+
+impl Anchors {
+    fn with_authorities(authorities: &[VerifyingKey]) -> Self {
+        Self {
+            authorities: authorities.iter().map(|k| (fingerprint(k), *k)).collect(),
+            roots: HashMap::new(),
+        }
+    }
+
+    fn load_dir(&mut self, dir: &Path, now: u64) -> Result<(), String> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            // Created on first publish.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(format!("{}: {e}", dir.display())),
+        };
+        for entry in entries {
+            let path = entry.map_err(|e| format!("{}: {e}", dir.display()))?.path();
+            if path.extension() != Some(OsStr::new("json")) {
+                continue;
+            }
+            let text = fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+            let announcement: RootAnnouncement =
+                serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+            match self.accept(announcement, now) {
+                Ok(_) => {}
+                // Left behind by a revoked authority key.
+                Err(RootError::UnknownAuthority) => {
+                    tracing::warn!(
+                        "{}: skipped, signer is not a residency authority",
+                        path.display()
+                    );
+                }
+                Err(e) => return Err(format!("{}: {}", path.display(), e.message())),
+            }
+        }
+        Ok(())
+    }
+
+    fn accept(
+        &mut self,
+        announcement: RootAnnouncement,
+        now: u64,
+    ) -> Result<RootAnnouncement, RootError> {
+        let root = &announcement.root;
+        if root.is_empty() || root.len() > MAX_ROOT_LEN || root.chars().any(char::is_whitespace) {
+            return Err(RootError::InvalidRoot);
+        }
+        let key = self
+            .authorities
+            .get(&announcement.kid)
+            .ok_or(RootError::UnknownAuthority)?;
+        let sig_bytes: [u8; 64] = URL_SAFE_NO_PAD
+            .decode(&announcement.signature)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or(RootError::BadSignature)?;
+        let message = signing_input(root, announcement.published_at);
+        key.verify(message.as_bytes(), &Signature::from_bytes(&sig_bytes))
+            .map_err(|_| RootError::BadSignature)?;
+        if announcement.published_at > now.saturating_add(CLOCK_SKEW_SECS) {
+            return Err(RootError::FromFuture);
+        }
+
+        // A re-announcement of a known root only ever moves it forward.
+        let entry = self
+            .roots
+            .entry(root.clone())
+            .or_insert_with(|| announcement.clone());
+        if announcement.published_at > entry.published_at {
+            *entry = announcement;
+        }
+        Ok(entry.clone())
+    }
+}
+
+pub struct RootRegistry {
+    anchors: RwLock<Anchors>,
+    /// Whether `reload` re-reads `RESIDENCY_AUTHORITY_KEYS`; registries
+    /// built with `new` keep the keys they were given.
+    keys_from_env: bool,
+    max_age_secs: u64,
+    dir: Option<PathBuf>,
+    /// Hash layout of the residency-set tree behind these roots.
+    tree_hash: TreeHash,
+}
+
+impl RootRegistry {
+    pub fn new(authorities: Vec<VerifyingKey>, max_age_secs: u64) -> Self {
+        Self {
+            anchors: RwLock::new(Anchors::with_authorities(&authorities)),
+            keys_from_env: false,
+            max_age_secs,
+            dir: None,
+            tree_hash: TreeHash::default(),
+        }
+    }
+
+    /// Configure from `RESIDENCY_AUTHORITY_KEYS` (comma-separated hex
+    /// public keys), `RESIDENCY_ROOT_MAX_AGE_DAYS`, `RESIDENCY_TREE_HASH`
+    /// (`sha256` or `poseidon`) and `RESIDENCY_ROOTS_DIR`, loading every
+    /// `*.json` announcement in the directory.  Announcements by keys not
+    /// (or no longer) listed are skipped with a warning; any other bad key
+    /// or announcement file is a startup error.
+    pub fn from_env(now: u64) -> Result<Self, String> {
+        let max_age_secs = match env::var("RESIDENCY_ROOT_MAX_AGE_DAYS") {
+            Ok(days) => parse_max_age_days(&days)?,
+            Err(_) => DEFAULT_MAX_ROOT_AGE_SECS,
+        };
+        let mut registry = Self::new(Vec::new(), max_age_secs);
+        registry.keys_from_env = true;
+        if let Ok(name) = env::var("RESIDENCY_TREE_HASH") {
+            registry.tree_hash = TreeHash::parse(&name)
+                .ok_or_else(|| format!("RESIDENCY_TREE_HASH: unknown layout `{name}`"))?;
+        }
+        registry.dir = env::var("RESIDENCY_ROOTS_DIR").ok().map(PathBuf::from);
+        registry.reload(now)?;
+        Ok(registry)
+    }
+
+    pub fn authority_count(&self) -> usize {
+        self.read().authorities.len()
+    }
+
+    pub fn tree_hash(&self) -> TreeHash {
+        self.tree_hash
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Anchors> {
+        self.anchors.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Verify and register an announcement, persisting it when a roots
+    /// directory is configured.
+    pub fn publish(
+        &self,
+        announcement: RootAnnouncement,
+        now: u64,
+    ) -> Result<PublishedRoot, RootError> {
+        let accepted = self
+            .anchors
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .accept(announcement, now)?;
+        if let Some(dir) = &self.dir {
+            if let Err(e) = persist(dir, &accepted) {
+                tracing::error!("cannot persist residency root {}: {e}", accepted.root);
+            }
+        }
+        Ok(self.status(&accepted, now))
+    }
+
+    /// Rebuild the trust anchors: authority keys from
+    /// `RESIDENCY_AUTHORITY_KEYS` (for registries built by `from_env`) and
+    /// roots from `RESIDENCY_ROOTS_DIR`, so added and revoked keys and
+    /// deleted announcements take effect.  Without a directory the current
+    /// roots are re-checked against the keys.  Nothing changes on error.
+    /// Returns how many roots are registered.
+    pub fn reload(&self, now: u64) -> Result<usize, String> {
+        let authorities = if self.keys_from_env {
+            Some(authorities_from_env()?)
+        } else {
+            None
+        };
+        self.rebuild(authorities, now)
+    }
+
+    fn rebuild(&self, authorities: Option<Vec<VerifyingKey>>, now: u64) -> Result<usize, String> {
+        let mut anchors = match &authorities {
+            Some(keys) => Anchors::with_authorities(keys),
+            None => Anchors {
+                authorities: self.read().authorities.clone(),
+                roots: HashMap::new(),
+            },
+        };
+        match &self.dir {
+            Some(dir) => anchors.load_dir(dir, now)?,
+            None => {
+                for announcement in self.read().roots.values() {
+                    // Roots whose authority is gone are dropped.
+                    let _ = anchors.accept(announcement.clone(), now);
+                }
+            }
+        }
+        let count = anchors.roots.len();
+        *self.anchors.write().unwrap_or_else(|e| e.into_inner()) = anchors;
+        Ok(count)
+    }
+
+    /// Is `root` registered and published within the freshness window?
+    pub fn is_fresh(&self, root: &str, now: u64) -> bool {
+        self.read()
+            .roots
+            .get(root)
+            .is_some_and(|a| now <= a.published_at.saturating_add(self.max_age_secs))
+    }
+
+    /// Every registered root, newest first.
+    pub fn snapshot(&self, now: u64) -> Vec<PublishedRoot> {
+        let anchors = self.read();
+        let mut listed: Vec<_> = anchors.roots.values().map(|a| self.status(a, now)).collect();
+        listed.sort_by(|a, b| b.published_at.cmp(&a.published_at).then(a.root.cmp(&b.root)));
+        listed
+    }
+
+    fn status(&self, announcement: &RootAnnouncement, now: u64) -> PublishedRoot {
+        let expires_at = announcement.published_at.saturating_add(self.max_age_secs);
+        PublishedRoot {
+            root: announcement.root.clone(),
+            published_at: announcement.published_at,
+            expires_at,
+            kid: announcement.kid.clone(),
+            fresh: now <= expires_at,
+        }
+    }
+}
+
+/// `RESIDENCY_AUTHORITY_KEYS`: comma-separated hex public keys.
+fn authorities_from_env() -> Result<Vec<VerifyingKey>, String> {
+    match env::var("RESIDENCY_AUTHORITY_KEYS") {
+        Ok(list) => list
+            .split(',')
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .map(parse_public_key)
+            .collect(),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+#[tracing::instrument(name = "store.persist_root", skip_all, fields(dir = ?dir))]
+fn persist(dir: &Path, announcement: &RootAnnouncement) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let digest = Sha256::digest(announcement.root.as_bytes());
+    let path = dir.join(format!("root-{}.json", hex::encode(&digest[..8])));
+    let json = serde_json::to_vec_pretty(announcement).expect("announcement serializes");
+    fs::write(path, json)
+}
+
+/// `RESIDENCY_ROOT_MAX_AGE_DAYS` in seconds.
+fn parse_max_age_days(days: &str) -> Result<u64, String> {
+    days.trim()
+        .parse::<u64>()
+        .map_err(|e| format!("RESIDENCY_ROOT_MAX_AGE_DAYS: {e}"))?
+        .checked_mul(24 * 60 * 60)
+        .ok_or_else(|| "RESIDENCY_ROOT_MAX_AGE_DAYS: too large".to_string())
+}
+
+fn parse_public_key(hex_key: &str) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = hex::decode(hex_key)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| {
+            format!("RESIDENCY_AUTHORITY_KEYS: `{hex_key}` is not a 32-byte hex public key")
+        })?;
+    VerifyingKey::from_bytes(&bytes)
+        .map_err(|e| format!("RESIDENCY_AUTHORITY_KEYS: `{hex_key}`: {e}"))
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_800_000_000;
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn authority() -> KeyRing {
+        KeyRing::from_seed([3; 32])
+    }
+
+    fn registry() -> RootRegistry {
+        RootRegistry::new(vec![authority().verifying_key()], DEFAULT_MAX_ROOT_AGE_SECS)
+    }
+
+    #[test]
+    fn fresh_until_window_closes() {
+        let registry = registry();
+        let published = registry
+            .publish(RootAnnouncement::sign(&authority(), "root-1", NOW), NOW)
+            .unwrap();
+        assert!(published.fresh);
+        assert_eq!(published.expires_at, NOW + 30 * DAY);
+        assert!(registry.is_fresh("root-1", NOW + 30 * DAY));
+        assert!(!registry.is_fresh("root-1", NOW + 30 * DAY + 1));
+        assert!(!registry.is_fresh("root-2", NOW));
+    }
+
+    #[test]
+    fn huge_windows_saturate_instead_of_wrapping() {
+        assert_eq!(parse_max_age_days(" 30 "), Ok(30 * DAY));
+        assert!(parse_max_age_days(&(u64::MAX / DAY + 1).to_string()).is_err());
+
+        let registry = RootRegistry::new(vec![authority().verifying_key()], u64::MAX);
+        let published = registry
+            .publish(RootAnnouncement::sign(&authority(), "root-1", NOW), NOW)
+            .unwrap();
+        assert_eq!(published.expires_at, u64::MAX);
+        assert!(registry.is_fresh("root-1", NOW + 100 * 365 * DAY));
+    }
+
+    #[test]
+    fn old_announcements_register_as_stale() {
+        let registry = registry();
+        let old = RootAnnouncement::sign(&authority(), "root-old", NOW - 31 * DAY);
+        assert!(!registry.publish(old, NOW).unwrap().fresh);
+        assert!(!registry.is_fresh("root-old", NOW));
+    }
+
+    #[test]
+    fn republication_moves_forward_only() {
+        let registry = registry();
+        let ring = authority();
+        registry
+            .publish(RootAnnouncement::sign(&ring, "root-1", NOW - 40 * DAY), NOW)
+            .unwrap();
+        registry
+            .publish(RootAnnouncement::sign(&ring, "root-1", NOW - DAY), NOW)
+            .unwrap();
+        registry
+            .publish(RootAnnouncement::sign(&ring, "root-1", NOW - 50 * DAY), NOW)
+            .unwrap();
+        assert_eq!(registry.snapshot(NOW)[0].published_at, NOW - DAY);
+    }
+
+    #[test]
+    fn rejects_unauthorized_or_tampered_announcements() {
+        let registry = registry();
+        let stranger = KeyRing::from_seed([4; 32]);
+        assert_eq!(
+            registry.publish(RootAnnouncement::sign(&stranger, "root-1", NOW), NOW),
+            Err(RootError::UnknownAuthority)
+        );
+
+        let mut forged = RootAnnouncement::sign(&authority(), "root-1", NOW - 40 * DAY);
+        forged.published_at = NOW;
+        assert_eq!(registry.publish(forged, NOW), Err(RootError::BadSignature));
+
+        let mut garbled = RootAnnouncement::sign(&authority(), "root-1", NOW);
+        garbled.signature = "not-base64!".to_string();
+        assert_eq!(registry.publish(garbled, NOW), Err(RootError::BadSignature));
+
+        assert_eq!(
+            registry.publish(RootAnnouncement::sign(&authority(), "root 1", NOW), NOW),
+            Err(RootError::InvalidRoot)
+        );
+        assert_eq!(
+            registry.publish(RootAnnouncement::sign(&authority(), "root-1", NOW + DAY), NOW),
+            Err(RootError::FromFuture)
+        );
+        assert!(registry.snapshot(NOW).is_empty());
+    }
+
+    #[test]
+    fn persists_and_reloads_from_dir() {
+        let dir = std::env::temp_dir().join(format!("residency-{:016x}", rand::random::<u64>()));
+        let mut registry = registry();
+        registry.dir = Some(dir.clone());
+        registry
+            .publish(RootAnnouncement::sign(&authority(), "root-1", NOW), NOW)
+            .unwrap();
+
+        let reloaded = registry_with_dir(&dir);
+        assert!(reloaded.is_fresh("root-1", NOW));
+
+        // Announcements dropped into the directory later arrive on reload.
+        let mut other = self::registry();
+        other.dir = Some(dir.clone());
+        other
+            .publish(RootAnnouncement::sign(&authority(), "root-2", NOW), NOW)
+            .unwrap();
+        assert!(!registry.is_fresh("root-2", NOW));
+        assert_eq!(registry.reload(NOW), Ok(2));
+        assert!(registry.is_fresh("root-2", NOW));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn registry_with_dir(dir: &Path) -> RootRegistry {
+        let mut registry = registry();
+        registry.dir = Some(dir.to_path_buf());
+        registry.reload(NOW).unwrap();
+        registry
+    }
+
+    #[test]
+    fn reload_drops_deleted_announcements_and_revoked_keys() {
+        let dir = std::env::temp_dir().join(format!("residency-{:016x}", rand::random::<u64>()));
+        let registry = registry_with_dir(&dir);
+        let other_authority = KeyRing::from_seed([5; 32]);
+        registry
+            .rebuild(
+                Some(vec![authority().verifying_key(), other_authority.verifying_key()]),
+                NOW,
+            )
+            .unwrap();
+        for (ring, root) in [
+            (authority(), "root-1"),
+            (authority(), "root-2"),
+            (other_authority, "root-3"),
+        ] {
+            registry
+                .publish(RootAnnouncement::sign(&ring, root, NOW), NOW)
+                .unwrap();
+        }
+
+        // Deleting an announcement file withdraws the root.
+        let removed = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .find(|p| fs::read_to_string(p).unwrap().contains("root-2"))
+            .unwrap();
+        fs::remove_file(removed).unwrap();
+        assert_eq!(registry.reload(NOW), Ok(2));
+        assert!(!registry.is_fresh("root-2", NOW));
+        assert!(registry.is_fresh("root-1", NOW));
+
+        // Revoking the second key withdraws the roots it signed.
+        assert_eq!(registry.rebuild(Some(vec![authority().verifying_key()]), NOW), Ok(1));
+        assert_eq!(registry.authority_count(), 1);
+        assert!(!registry.is_fresh("root-3", NOW));
+
+        // A corrupt file fails the reload and leaves the anchors as they were.
+        fs::write(dir.join("broken.json"), "{").unwrap();
+        assert!(registry.reload(NOW).is_err());
+        assert!(registry.is_fresh("root-1", NOW));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reload_without_a_dir_rechecks_roots_against_the_keys() {
+        let registry = registry();
+        registry
+            .publish(RootAnnouncement::sign(&authority(), "root-1", NOW), NOW)
+            .unwrap();
+        assert_eq!(registry.reload(NOW), Ok(1));
+        let replacement = KeyRing::from_seed([5; 32]);
+        assert_eq!(registry.rebuild(Some(vec![replacement.verifying_key()]), NOW), Ok(0));
+        assert!(!registry.is_fresh("root-1", NOW));
+        assert_eq!(
+            registry.publish(RootAnnouncement::sign(&authority(), "root-1", NOW), NOW),
+            Err(RootError::UnknownAuthority)
+        );
+    }
+
+    #[test]
+    fn public_key_parsing() {
+        let hex_key = hex::encode(authority().verifying_key().as_bytes());
+        assert_eq!(parse_public_key(&hex_key).unwrap(), authority().verifying_key());
+        assert!(parse_public_key("abcd").is_err());
+    }
+}
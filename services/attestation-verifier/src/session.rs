@@ -0,0 +1,360 @@
+//! Signed session tokens.
+//!
+//! Tokens are compact JWS (`header.claims.signature`, base64url, EdDSA) so
+//! any service holding the verifier's public key can check them.  The
+//! claims carry what trust gates need: the principal nullifier and the
+//! trust score the session was issued with.
+//!
+//! Operators can revoke sessions before they expire, by `jti` or for every
+//! session of a principal nullifier issued up to the revocation.  The list
+//! is kept in `$VERIFIER_STATE_DIR/revocations.json` and pruned once no
+//! revoked token could still be unexpired.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::Signature;
+use serde::{Deserialize, Serialize};
+
+use crate::keys::KeyRing;
+use crate::store::JsonFile;
+use crate::verdict::AssuranceLevel;
+
+/// Default session lifetime: 7 days (identity spec §2.1.2, Silver).
+pub const DEFAULT_SESSION_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Longest `SESSION_TTL_SECS` accepted: one year.
+pub const MAX_SESSION_TTL_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// `SESSION_TTL_SECS`, or the default.
+pub fn ttl_from_env() -> Result<u64, String> {
+    match std::env::var("SESSION_TTL_SECS") {
+        Ok(value) => parse_ttl(&value),
+        Err(_) => Ok(DEFAULT_SESSION_TTL_SECS),
+    }
+}
+
+fn parse_ttl(value: &str) -> Result<u64, String> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|ttl| (1..=MAX_SESSION_TTL_SECS).contains(ttl))
+        .ok_or_else(|| {
+            format!("SESSION_TTL_SECS must be whole seconds from 1 to {MAX_SESSION_TTL_SECS}")
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionClaims {
+    /// Unique token id.
+    pub jti: String,
+    /// Principal `UniquenessNullifier`.
+    pub sub: String,
+    /// Trust score at issuance, `[0, 1]`.
+    pub score: f32,
+    pub assurance: AssuranceLevel,
+    /// Issued-at, unix seconds.
+    pub iat: u64,
+    /// Expiry, unix seconds.
+    pub exp: u64,
+}
+
+impl SessionClaims {
+    pub fn scaled_score(&self) -> u32 {
+        scale(self.score)
+    }
+}
+
+/// JWS protected header.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Header {
+    pub alg: String,
+    pub typ: String,
+    pub kid: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenError {
+    Malformed,
+    UnknownKey,
+    BadSignature,
+    Expired,
+    Revoked,
+}
+
+impl TokenError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TokenError::Malformed => "MALFORMED_TOKEN",
+            TokenError::UnknownKey => "UNKNOWN_SIGNING_KEY",
+            TokenError::BadSignature => "INVALID_TOKEN_SIGNATURE",
+            TokenError::Expired => "SESSION_EXPIRED",
+            TokenError::Revoked => "SESSION_REVOKED",
+        }
+    }
+}
+
+/// `ScaledTrustScore = round(trustScore * 10000)` (identity spec §1).
+pub fn scale(score: f32) -> u32 {
+    (score.clamp(0.0, 1.0) * 10_000.0).round() as u32
+}
+
+pub fn issue(keys: &KeyRing, claims: &SessionClaims) -> String {
+    let header = Header {
+        alg: "EdDSA".to_string(),
+        typ: "JWT".to_string(),
+        kid: keys.kid().to_string(),
+    };
+    let signing_input = format!(
+        "{}.{}",
+        encode_json(&header),
+        encode_json(claims)
+    );
+    let signature = keys.sign(signing_input.as_bytes());
+    format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+}
+
+/// Decode without checking the signature or expiry (for inspection only).
+pub fn decode_unverified(token: &str) -> Result<SessionClaims, TokenError> {
+    let mut parts = token.split('.');
+    let (Some(_), Some(claims), Some(_), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(TokenError::Malformed);
+    };
+    decode_json(claims)
+}
+
+/// The header, unverified (for inspection only).
+pub fn decode_header(token: &str) -> Result<Header, TokenError> {
+    let header = token.split('.').next().ok_or(TokenError::Malformed)?;
+    decode_json(header)
+}
+
+/// Check signature and expiry; `now` is unix seconds.
+pub fn verify(keys: &KeyRing, token: &str, now: u64) -> Result<SessionClaims, TokenError> {
+    let mut parts = token.split('.');
+    let (Some(header_b64), Some(claims_b64), Some(sig_b64), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(TokenError::Malformed);
+    };
+    let header: Header = decode_json(header_b64)?;
+    if header.alg != "EdDSA" {
+        return Err(TokenError::Malformed);
+    }
+    let sig_bytes: [u8; 64] = URL_SAFE_NO_PAD
+        .decode(sig_b64)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or(TokenError::Malformed)?;
+    if !keys.knows(&header.kid) {
+        return Err(TokenError::UnknownKey);
+    }
+    let signing_input = &token[..header_b64.len() + 1 + claims_b64.len()];
+    if !keys.verify(&header.kid, signing_input.as_bytes(), &Signature::from_bytes(&sig_bytes)) {
+        return Err(TokenError::BadSignature);
+    }
+    let claims: SessionClaims = decode_json(claims_b64)?;
+    if now >= claims.exp {
+        return Err(TokenError::Expired);
+    }
+    Ok(claims)
+}
+
+// ── revocation ─────────────────────────────────────────────────────────
+
+/// Revocation times (unix seconds) by `jti` and by principal nullifier.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RevocationList {
+    jtis: BTreeMap<String, u64>,
+    nullifiers: BTreeMap<String, u64>,
+}
+
+pub struct Revocations {
+    list: Mutex<RevocationList>,
+    file: JsonFile,
+}
+
+impl Revocations {
+    pub fn new(file: JsonFile) -> Result<Self, String> {
+        Ok(Self {
+            list: Mutex::new(file.load()?),
+            file,
+        })
+    }
+
+    pub fn memory() -> Self {
+        Self {
+            list: Mutex::new(RevocationList::default()),
+            file: JsonFile::memory(),
+        }
+    }
+
+    /// `$VERIFIER_STATE_DIR/revocations.json`.
+    pub fn from_env() -> Result<Self, String> {
+        Self::new(JsonFile::from_env("revocations.json"))
+    }
+
+    /// Revoke one session.  `ttl_secs` is the longest a session can live,
+    /// after which the entry is pruned.
+    pub fn revoke_jti(&self, jti: &str, now: u64, ttl_secs: u64) {
+        self.update(now, ttl_secs, |list| {
+            list.jtis.insert(jti.to_string(), now);
+        });
+    }
+
+    /// Revoke every session of `nullifier` issued up to `now`.
+    pub fn revoke_nullifier(&self, nullifier: &str, now: u64, ttl_secs: u64) {
+        self.update(now, ttl_secs, |list| {
+            list.nullifiers.insert(nullifier.to_string(), now);
+        });
+    }
+
+    pub fn is_revoked(&self, claims: &SessionClaims) -> bool {
+        let list = self.list.lock().unwrap_or_else(|e| e.into_inner());
+        list.jtis.contains_key(&claims.jti)
+            || list
+                .nullifiers
+                .get(&claims.sub)
+                .is_some_and(|&revoked_at| claims.iat <= revoked_at)
+    }
+
+    /// When sessions of `nullifier` were last revoked.
+    pub fn nullifier_revoked_at(&self, nullifier: &str) -> Option<u64> {
+        let list = self.list.lock().unwrap_or_else(|e| e.into_inner());
+        list.nullifiers.get(nullifier).copied()
+    }
+
+    fn update(&self, now: u64, ttl_secs: u64, change: impl FnOnce(&mut RevocationList)) {
+        let mut list = self.list.lock().unwrap_or_else(|e| e.into_inner());
+        change(&mut list);
+        let live = |revoked_at: &mut u64| now < revoked_at.saturating_add(ttl_secs);
+        list.jtis.retain(|_, at| live(at));
+        list.nullifiers.retain(|_, at| live(at));
+        if let Err(e) = self.file.save(&*list) {
+            tracing::error!("cannot persist session revocations: {e}");
+        }
+    }
+}
+
+fn encode_json<T: Serialize>(value: &T) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(value).expect("token parts serialize"))
+}
+
+fn decode_json<T: for<'de> Deserialize<'de>>(part: &str) -> Result<T, TokenError> {
+    let bytes = URL_SAFE_NO_PAD.decode(part).map_err(|_| TokenError::Malformed)?;
+    serde_json::from_slice(&bytes).map_err(|_| TokenError::Malformed)
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(exp: u64) -> SessionClaims {
+        SessionClaims {
+            jti: "j1".to_string(),
+            sub: "nullifier-abc".to_string(),
+            score: 0.8,
+            assurance: AssuranceLevel::Silver,
+            iat: 100,
+            exp,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let keys = KeyRing::generate();
+        let token = issue(&keys, &claims(200));
+        assert_eq!(token.split('.').count(), 3);
+        assert_eq!(verify(&keys, &token, 150).unwrap(), claims(200));
+        assert_eq!(decode_unverified(&token).unwrap().sub, "nullifier-abc");
+    }
+
+    #[test]
+    fn rejects_expired() {
+        let keys = KeyRing::generate();
+        let token = issue(&keys, &claims(200));
+        assert_eq!(verify(&keys, &token, 200), Err(TokenError::Expired));
+    }
+
+    #[test]
+    fn rejects_foreign_key_and_tampering() {
+        let keys = KeyRing::generate();
+        let other = KeyRing::generate();
+        let token = issue(&other, &claims(200));
+        assert_eq!(verify(&keys, &token, 150), Err(TokenError::UnknownKey));
+
+        let token = issue(&keys, &claims(200));
+        let mut parts: Vec<&str> = token.split('.').collect();
+        let forged = encode_json(&SessionClaims {
+            score: 1.0,
+            ..claims(200)
+        });
+        parts[1] = &forged;
+        assert_eq!(
+            verify(&keys, &parts.join("."), 150),
+            Err(TokenError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        let keys = KeyRing::generate();
+        assert_eq!(verify(&keys, "session-123", 0), Err(TokenError::Malformed));
+        assert_eq!(verify(&keys, "a.b.c", 0), Err(TokenError::Malformed));
+        assert_eq!(verify(&keys, "a.b.c.d", 0), Err(TokenError::Malformed));
+    }
+
+    #[test]
+    fn revokes_by_jti_and_by_nullifier() {
+        let revocations = Revocations::memory();
+        assert!(!revocations.is_revoked(&claims(200)));
+
+        revocations.revoke_jti("j1", 150, 1_000);
+        assert!(revocations.is_revoked(&claims(200)));
+        assert!(!revocations.is_revoked(&SessionClaims {
+            jti: "j2".to_string(),
+            ..claims(200)
+        }));
+
+        // Only sessions issued up to the revocation are affected.
+        revocations.revoke_nullifier("nullifier-abc", 150, 1_000);
+        assert_eq!(revocations.nullifier_revoked_at("nullifier-abc"), Some(150));
+        let earlier = SessionClaims {
+            jti: "j3".to_string(),
+            ..claims(200)
+        };
+        let later = SessionClaims { iat: 151, ..earlier.clone() };
+        assert!(revocations.is_revoked(&earlier));
+        assert!(!revocations.is_revoked(&later));
+    }
+
+    #[test]
+    fn session_ttl_must_be_in_range() {
+        assert_eq!(parse_ttl(" 3600 "), Ok(3600));
+        assert!(parse_ttl("0").is_err());
+        assert!(parse_ttl("7d").is_err());
+        assert!(parse_ttl(&u64::MAX.to_string()).is_err());
+    }
+
+    #[test]
+    fn revocations_are_pruned_after_the_session_ttl() {
+        let revocations = Revocations::memory();
+        revocations.revoke_jti("j1", 100, 50);
+        revocations.revoke_jti("j2", 160, 50);
+        assert!(!revocations.is_revoked(&claims(200)));
+    }
+
+    #[test]
+    fn scaling_matches_spec() {
+        assert_eq!(scale(0.5), 5000);
+        assert_eq!(scale(0.12345), 1235);
+        assert_eq!(scale(1.5), 10_000);
+    }
+}
@@ -0,0 +1,128 @@
+//! Durable verifier state.
+//!
+//! State that must survive a restart (budget ledgers, …) is kept as JSON
+//! snapshots under `VERIFIER_STATE_DIR`.  Without it the store is
+//! memory-only, which is fine for DEV but means counters reset on restart.
+//! Writes go to a temporary file first and are renamed into place, so a
+//! crash mid-write leaves the previous snapshot intact.
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// One JSON snapshot file, or nothing when running memory-only.
+#[derive(Debug, Clone, Default)]
+pub struct JsonFile {
+    path: Option<PathBuf>,
+}
+
+impl JsonFile {
+    pub fn memory() -> Self {
+        Self { path: None }
+    }
+
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// `$VERIFIER_STATE_DIR/<name>`, or memory-only when unset.
+    pub fn from_env(name: &str) -> Self {
+        match env::var("VERIFIER_STATE_DIR") {
+            Ok(dir) => Self::at(Path::new(&dir).join(name)),
+            Err(_) => Self::memory(),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Read the snapshot; a missing file (or memory-only store) yields the
+    /// default value.
+    #[tracing::instrument(name = "store.load", skip_all, fields(path = ?self.path))]
+    pub fn load<T: DeserializeOwned + Default>(&self) -> Result<T, String> {
+        let Some(path) = &self.path else {
+            return Ok(T::default());
+        };
+        match fs::read(path) {
+            Ok(bytes) => {
+                serde_json::from_slice(&bytes).map_err(|e| format!("{}: {e}", path.display()))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(T::default()),
+            Err(e) => Err(format!("{}: {e}", path.display())),
+        }
+    }
+
+    /// Replace the snapshot atomically.  A no-op when memory-only.
+    #[tracing::instrument(name = "store.save", skip_all, fields(path = ?self.path))]
+    pub fn save<T: Serialize>(&self, value: &T) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let describe = |e: std::io::Error| format!("{}: {e}", path.display());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(describe)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        let json = serde_json::to_vec(value).map_err(|e| format!("{}: {e}", path.display()))?;
+        fs::write(&tmp, json).map_err(describe)?;
+        fs::rename(&tmp, path).map_err(describe)
+    }
+
+    /// Can a snapshot be written here right now?  Writes and removes a
+    /// probe file next to the snapshot; always `Ok` when memory-only.
+    pub fn check_writable(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let describe = |e: std::io::Error| format!("{}: {e}", path.display());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(describe)?;
+        }
+        let probe = path.with_extension("json.probe");
+        fs::write(&probe, b"{}").map_err(describe)?;
+        fs::remove_file(&probe).map_err(describe)
+    }
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn memory_store_round_trips_to_default() {
+        let store = JsonFile::memory();
+        store.save(&BTreeMap::from([("a", 1)])).unwrap();
+        let loaded: BTreeMap<String, u32> = store.load().unwrap();
+        assert!(loaded.is_empty());
+    }
+
+    #[test]
+    fn file_store_persists_and_replaces() {
+        let dir = std::env::temp_dir().join(format!("store-{:016x}", rand::random::<u64>()));
+        let store = JsonFile::at(dir.join("nested").join("state.json"));
+        let empty: BTreeMap<String, u32> = store.load().unwrap();
+        assert!(empty.is_empty());
+
+        store.save(&BTreeMap::from([("a".to_string(), 1)])).unwrap();
+        store.save(&BTreeMap::from([("b".to_string(), 2)])).unwrap();
+        let loaded: BTreeMap<String, u32> = store.load().unwrap();
+        assert_eq!(loaded, BTreeMap::from([("b".to_string(), 2)]));
+
+        store.check_writable().unwrap();
+        assert!(!dir.join("nested").join("state.json.probe").exists());
+
+        fs::write(store.path().unwrap(), b"{not json").unwrap();
+        assert!(store.load::<BTreeMap<String, u32>>().is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
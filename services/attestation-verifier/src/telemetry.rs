@@ -0,0 +1,130 @@
+//! OpenTelemetry trace export.
+//!
+//! Off unless `OTEL_EXPORTER_OTLP_ENDPOINT` is set (e.g.
+//! `http://otel-collector:4317`); spans then go to that collector over
+//! OTLP/gRPC alongside the JSON logs.  `OTEL_TRACES_SAMPLER_ARG` is the
+//! sampling ratio for new traces (default `1.0`); requests arriving with a
+//! W3C `traceparent` follow the caller's sampling decision, so a
+//! PWA → Traefik → verifier flow stays in one trace.  `OTEL_SERVICE_NAME`
+//! defaults to `attestation-verifier`.
+//!
+//! Span attributes bypass the log formatter's redaction, so sensitive
+//! values must reach tracing macros through `logging::Redacted`.
+
+use std::env;
+
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::{global, Context, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{self, Sampler, Tracer};
+use opentelemetry_sdk::Resource;
+use warp::http::HeaderMap;
+
+const DEFAULT_SERVICE_NAME: &str = "attestation-verifier";
+
+/// Build the OTLP tracer, or `None` when no endpoint is configured.
+pub fn tracer_from_env() -> Result<Option<Tracer>, String> {
+    let Ok(endpoint) = env::var("OTEL_EXPORTER_OTLP_ENDPOINT") else {
+        return Ok(None);
+    };
+    let ratio = match env::var("OTEL_TRACES_SAMPLER_ARG") {
+        Ok(value) => parse_ratio(&value)?,
+        Err(_) => 1.0,
+    };
+    let service_name =
+        env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_string());
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            trace::config()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                    ratio,
+                ))))
+                .with_resource(Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    service_name,
+                )])),
+        )
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+        .map(Some)
+        .map_err(|e| format!("OTEL_EXPORTER_OTLP_ENDPOINT: {e}"))
+}
+
+/// Flush buffered spans; call before the process exits.
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+/// The remote parent carried by an incoming `traceparent`, if any.
+pub fn remote_context(headers: &HeaderMap) -> Context {
+    TraceContextPropagator::new().extract(&Headers(headers))
+}
+
+fn parse_ratio(value: &str) -> Result<f64, String> {
+    match value.trim().parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err(format!(
+            "OTEL_TRACES_SAMPLER_ARG must be a ratio in [0, 1], got {value:?}"
+        )),
+    }
+}
+
+struct Headers<'a>(&'a HeaderMap);
+
+impl Extractor for Headers<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+
+    #[test]
+    fn extracts_w3c_traceparent() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".parse().unwrap(),
+        );
+        let context = remote_context(&headers);
+        let span = context.span();
+        let parent = span.span_context();
+        assert!(parent.is_remote());
+        assert!(parent.is_sampled());
+        assert_eq!(parent.trace_id().to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(parent.span_id().to_string(), "00f067aa0ba902b7");
+    }
+
+    #[test]
+    fn missing_or_malformed_traceparent_has_no_parent() {
+        let mut headers = HeaderMap::new();
+        assert!(!remote_context(&headers).span().span_context().is_valid());
+        headers.insert("traceparent", "00-zz-00f067aa0ba902b7-01".parse().unwrap());
+        assert!(!remote_context(&headers).span().span_context().is_valid());
+    }
+
+    #[test]
+    fn sampling_ratio_is_bounded() {
+        assert_eq!(parse_ratio("0.25"), Ok(0.25));
+        assert_eq!(parse_ratio(" 1 "), Ok(1.0));
+        assert!(parse_ratio("1.5").is_err());
+        assert!(parse_ratio("half").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! Canonical `TRUST_THRESHOLDS` table (identity spec §2).
+//!
+//! The single source for every trust-gated surface.  Served at
+//! `/policy/thresholds` and enforced by `/authorize`, so relay, services
+//! and clients gate on the same numbers instead of inline 0.5/0.7s.
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::session::scale;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Threshold {
+    pub surface: &'static str,
+    pub threshold: f32,
+    pub scaled: u32,
+    pub rationale: &'static str,
+}
+
+const fn gate(surface: &'static str, threshold: f32, scaled: u32, rationale: &'static str) -> Threshold {
+    Threshold {
+        surface,
+        threshold,
+        scaled,
+        rationale,
+    }
+}
+
+/// Season 0 thresholds.  `scaled` is `round(threshold * 10000)`.
+pub const TRUST_THRESHOLDS: &[Threshold] = &[
+    gate("session_creation", 0.5, 5000, "Minimum bar for verified human"),
+    gate("forum_participation", 0.5, 5000, "Read/write gating for threads"),
+    gate("mesh_write", 0.5, 5000, "Prevents unauthenticated mesh writes"),
+    gate("cak_view_reps", 0.5, 5000, "Rep directory browsing"),
+    gate("cak_draft", 0.5, 5000, "Compose civic packet"),
+    gate("bridge_access", 0.5, 5000, "Civic bridge entry point"),
+    gate("ube_claim", 0.5, 5000, "Daily Boost eligibility"),
+    gate("dashboard_tier_basic", 0.5, 5000, "Visual tier indicator"),
+    gate("dashboard_tier_high", 0.7, 7000, "Visual tier indicator"),
+    gate("governance_vote", 0.7, 7000, "High-impact civic action"),
+    gate("cak_send", 0.7, 7000, "Outbound civic forwarding"),
+    gate("moderation", 0.7, 7000, "Placeholder - not yet implemented"),
+];
+
+pub fn lookup(surface: &str) -> Option<&'static Threshold> {
+    TRUST_THRESHOLDS.iter().find(|t| t.surface == surface)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Decision {
+    Allowed,
+    TrustBelowThreshold,
+    SessionExpired,
+}
+
+/// Gate `score` against `gate`, with the spec's explanatory message
+/// (§2.1.5: "Trust score (0.45) below 0.50 - verify identity to continue").
+pub fn decide(gate: &Threshold, score: f32) -> (Decision, String) {
+    if scale(score) >= gate.scaled {
+        (
+            Decision::Allowed,
+            format!("Trust score ({score:.2}) meets {:.2} for {}", gate.threshold, gate.surface),
+        )
+    } else {
+        (
+            Decision::TrustBelowThreshold,
+            format!(
+                "Trust score ({score:.2}) below {:.2} - verify identity to continue",
+                gate.threshold
+            ),
+        )
+    }
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_values_match_thresholds() {
+        for t in TRUST_THRESHOLDS {
+            assert_eq!(t.scaled, scale(t.threshold), "{}", t.surface);
+        }
+    }
+
+    #[test]
+    fn surfaces_are_unique() {
+        for (i, t) in TRUST_THRESHOLDS.iter().enumerate() {
+            assert!(
+                TRUST_THRESHOLDS[i + 1..].iter().all(|o| o.surface != t.surface),
+                "duplicate {}",
+                t.surface
+            );
+        }
+    }
+
+    #[test]
+    fn decisions_use_scaled_comparison() {
+        let vote = lookup("governance_vote").unwrap();
+        assert_eq!(decide(vote, 0.7).0, Decision::Allowed);
+        let (decision, message) = decide(vote, 0.45);
+        assert_eq!(decision, Decision::TrustBelowThreshold);
+        assert_eq!(message, "Trust score (0.45) below 0.70 - verify identity to continue");
+        assert!(lookup("nope").is_none());
+    }
+}
@@ -0,0 +1,622 @@
+//! Public transparency log of issued attestations.
+//!
+//! Every issued session appends a leaf committing to the nullifier, the
+//! scaled trust score and the issue time.  The leaves form an RFC 9162
+//! Merkle tree (`leaf = H(0x00 ‖ input)`, `node = H(0x01 ‖ left ‖ right)`,
+//! SHA-256) whose head the verifier signs with its session key.  Anyone
+//! holding two signed heads can demand a consistency proof (the log only
+//! grew), and anyone holding a leaf can demand an inclusion proof, so a
+//! verifier that hands out extra nullifiers must either log them — where
+//! auditors count them — or fork the log, which gossiped heads expose.
+//!
+//! The nullifier itself is never published, only
+//! `hex(SHA-256("vh-nullifier-commitment/v1\n" ‖ nullifier))`; a holder can
+//! recompute it to find their own entries.  Leaves are appended to
+//! `$VERIFIER_STATE_DIR/transparency.ndjson` (memory-only without it).
+
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::keys::KeyRing;
+use crate::merkle::Node;
+
+/// Domain separator for nullifier commitments.
+const COMMITMENT_CONTEXT: &str = "vh-nullifier-commitment/v1";
+
+/// Domain separator for tree head signatures.
+const TREE_HEAD_CONTEXT: &str = "vh-transparency-sth/v1";
+
+/// Most entries returned by one `/transparency/entries` page.
+pub const MAX_PAGE: u64 = 256;
+
+/// One issued attestation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LogEntry {
+    pub index: u64,
+    /// See `commitment`.
+    pub commitment: String,
+    /// Trust score × 10000.
+    pub scaled_score: u32,
+    /// Unix seconds.
+    pub issued_at: u64,
+}
+
+impl LogEntry {
+    /// The hashed leaf input; the index is implied by position.
+    fn leaf_input(&self) -> String {
+        format!("{}\n{}\n{}", self.commitment, self.scaled_score, self.issued_at)
+    }
+
+    pub fn leaf_hash(&self) -> Node {
+        let mut hasher = Sha256::new();
+        hasher.update([0x00]);
+        hasher.update(self.leaf_input().as_bytes());
+        hasher.finalize().into()
+    }
+}
+
+/// How a nullifier appears in the log.
+pub fn commitment(nullifier: &str) -> String {
+    hex::encode(Sha256::digest(
+        format!("{COMMITMENT_CONTEXT}\n{nullifier}").as_bytes(),
+    ))
+}
+
+fn node_hash(left: &Node, right: &Node) -> Node {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Largest power of two strictly below `n` (`n >= 2`).
+fn split(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+/// A signed statement that the log held `tree_size` leaves with root
+/// `root_hash` at `timestamp`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedTreeHead {
+    pub tree_size: u64,
+    /// Hex.
+    pub root_hash: String,
+    /// Unix seconds.
+    pub timestamp: u64,
+    pub kid: String,
+    /// Hex Ed25519 public key for `kid`.
+    pub public_key: String,
+    /// Base64url Ed25519 signature over `signing_input()`.
+    pub signature: String,
+}
+
+pub fn signing_input(tree_size: u64, root_hash: &str, timestamp: u64) -> String {
+    format!("{TREE_HEAD_CONTEXT}\n{tree_size}\n{root_hash}\n{timestamp}")
+}
+
+/// Leaves plus the root of every complete subtree, kept up to date on
+/// append so heads and proofs cost O(log n) hashes rather than O(n).
+struct Tree {
+    entries: Vec<LogEntry>,
+    /// `levels[h][i]` is the root of leaves `i << h .. (i + 1) << h`;
+    /// `levels[0]` holds the leaves themselves.
+    levels: Vec<Vec<Node>>,
+}
+
+impl Tree {
+    fn size(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn push(&mut self, entry: LogEntry) {
+        let mut node = entry.leaf_hash();
+        self.entries.push(entry);
+        for height in 0.. {
+            if self.levels.len() == height {
+                self.levels.push(Vec::new());
+            }
+            let level = &mut self.levels[height];
+            level.push(node);
+            if level.len() % 2 == 1 {
+                break;
+            }
+            node = node_hash(&level[level.len() - 2], &level[level.len() - 1]);
+        }
+    }
+
+    /// RFC 9162 §2.1.1 tree hash of leaves `start..start + n` (`n >= 1`).
+    fn hash(&self, start: usize, n: usize) -> Node {
+        if n.is_power_of_two() && start & (n - 1) == 0 {
+            return self.levels[n.trailing_zeros() as usize][start / n];
+        }
+        let k = split(n);
+        node_hash(&self.hash(start, k), &self.hash(start + k, n - k))
+    }
+
+    /// Root of the first `n` leaves.
+    fn root(&self, n: usize) -> Node {
+        if n == 0 {
+            return Sha256::digest([]).into();
+        }
+        self.hash(0, n)
+    }
+
+    /// RFC 9162 §2.1.3.1 audit path for leaf `m` of leaves
+    /// `start..start + n`.
+    fn inclusion_path(&self, m: usize, start: usize, n: usize) -> Vec<Node> {
+        if n <= 1 {
+            return Vec::new();
+        }
+        let k = split(n);
+        let (mut path, sibling) = if m < k {
+            (self.inclusion_path(m, start, k), self.hash(start + k, n - k))
+        } else {
+            (self.inclusion_path(m - k, start + k, n - k), self.hash(start, k))
+        };
+        path.push(sibling);
+        path
+    }
+
+    /// RFC 9162 §2.1.4.1 `SUBPROOF(m, leaves, complete)` over leaves
+    /// `start..start + n`.
+    fn subproof(&self, m: usize, start: usize, n: usize, complete: bool) -> Vec<Node> {
+        if m == n {
+            return if complete { Vec::new() } else { vec![self.hash(start, n)] };
+        }
+        let k = split(n);
+        let (mut proof, sibling) = if m <= k {
+            (self.subproof(m, start, k, complete), self.hash(start + k, n - k))
+        } else {
+            (self.subproof(m - k, start + k, n - k, false), self.hash(start, k))
+        };
+        proof.push(sibling);
+        proof
+    }
+}
+
+pub struct TransparencyLog {
+    tree: RwLock<Tree>,
+    /// Last signed head; reused until the log grows or the key rotates.
+    head: Mutex<Option<SignedTreeHead>>,
+    path: Option<PathBuf>,
+}
+
+impl TransparencyLog {
+    pub fn memory() -> Self {
+        Self {
+            tree: RwLock::new(Tree {
+                entries: Vec::new(),
+                levels: Vec::new(),
+            }),
+            head: Mutex::new(None),
+            path: None,
+        }
+    }
+
+    /// Continue the log in `path`; entries must be numbered from 0 with no
+    /// gaps.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let log = Self::memory();
+        let file = match fs::File::open(&path) {
+            Ok(file) => Some(file),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        };
+        if let Some(file) = file {
+            let mut tree = log.tree.write().unwrap_or_else(|e| e.into_inner());
+            for (line, text) in BufReader::new(file).lines().enumerate() {
+                let text = text.map_err(|e| format!("{}: {e}", path.display()))?;
+                let entry: LogEntry = serde_json::from_str(&text)
+                    .map_err(|e| format!("{}: line {}: {e}", path.display(), line + 1))?;
+                if entry.index != line as u64 {
+                    return Err(format!(
+                        "{}: line {} holds entry {}",
+                        path.display(),
+                        line + 1,
+                        entry.index
+                    ));
+                }
+                tree.push(entry);
+            }
+        }
+        Ok(Self {
+            path: Some(path),
+            ..log
+        })
+    }
+
+    /// `$VERIFIER_STATE_DIR/transparency.ndjson`, or memory-only when unset.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("VERIFIER_STATE_DIR") {
+            Ok(dir) => Self::open(Path::new(&dir).join("transparency.ndjson")),
+            Err(_) => Ok(Self::memory()),
+        }
+    }
+
+    /// Log an issued attestation.  Unlike the audit log a write failure is
+    /// returned: a session that is not in the log must not be issued.
+    pub fn append(
+        &self,
+        nullifier: &str,
+        scaled_score: u32,
+        issued_at: u64,
+    ) -> Result<LogEntry, String> {
+        let mut tree = self.tree.write().unwrap_or_else(|e| e.into_inner());
+        let entry = LogEntry {
+            index: tree.size() as u64,
+            commitment: commitment(nullifier),
+            scaled_score,
+            issued_at,
+        };
+        if let Some(path) = &self.path {
+            append_line(path, &entry).map_err(|e| format!("{}: {e}", path.display()))?;
+        }
+        tree.push(entry.clone());
+        Ok(entry)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.tree.read().unwrap_or_else(|e| e.into_inner()).entries.len() as u64
+    }
+
+    /// The signed current head.  A head is signed once per tree size and
+    /// key, so its `timestamp` is when the log first reached that size.
+    pub fn tree_head(&self, keys: &KeyRing, now: u64) -> SignedTreeHead {
+        let (tree_size, root) = {
+            let tree = self.tree.read().unwrap_or_else(|e| e.into_inner());
+            (tree.size() as u64, tree.root(tree.size()))
+        };
+        let mut cached = self.head.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(head) = cached
+            .as_ref()
+            .filter(|h| h.tree_size == tree_size && h.kid == keys.kid())
+        {
+            return head.clone();
+        }
+        let root_hash = hex::encode(root);
+        let signature = keys.sign(signing_input(tree_size, &root_hash, now).as_bytes());
+        let head = SignedTreeHead {
+            tree_size,
+            root_hash,
+            timestamp: now,
+            kid: keys.kid().to_string(),
+            public_key: hex::encode(keys.verifying_key().as_bytes()),
+            signature: URL_SAFE_NO_PAD.encode(signature.to_bytes()),
+        };
+        *cached = Some(head.clone());
+        head
+    }
+
+    /// Root of the first `tree_size` leaves; `None` beyond the log.
+    pub fn root(&self, tree_size: u64) -> Option<Node> {
+        let tree = self.tree.read().unwrap_or_else(|e| e.into_inner());
+        let tree_size = usize::try_from(tree_size).ok().filter(|&n| n <= tree.size())?;
+        Some(tree.root(tree_size))
+    }
+
+    /// Entries `start..end`, clamped to the log and to `MAX_PAGE`.
+    pub fn entries(&self, start: u64, end: u64) -> Vec<LogEntry> {
+        let tree = self.tree.read().unwrap_or_else(|e| e.into_inner());
+        let len = tree.entries.len() as u64;
+        let end = end.min(len).min(start.saturating_add(MAX_PAGE));
+        if start >= end {
+            return Vec::new();
+        }
+        tree.entries[start as usize..end as usize].to_vec()
+    }
+
+    /// Every entry for `commitment`.
+    pub fn find(&self, commitment: &str) -> Vec<LogEntry> {
+        let tree = self.tree.read().unwrap_or_else(|e| e.into_inner());
+        tree.entries
+            .iter()
+            .filter(|entry| entry.commitment == commitment)
+            .cloned()
+            .collect()
+    }
+
+    /// Audit path proving leaf `index` is in the tree of `tree_size`
+    /// leaves, with that leaf's hash.  `None` unless
+    /// `index < tree_size <= size()`.
+    pub fn inclusion_proof(&self, index: u64, tree_size: u64) -> Option<(Node, Vec<Node>)> {
+        let tree = self.tree.read().unwrap_or_else(|e| e.into_inner());
+        let tree_size = usize::try_from(tree_size).ok().filter(|&n| n <= tree.size())?;
+        let index = usize::try_from(index).ok().filter(|&i| i < tree_size)?;
+        Some((tree.levels[0][index], tree.inclusion_path(index, 0, tree_size)))
+    }
+
+    /// Proof that the tree of `first` leaves is a prefix of the tree of
+    /// `second` leaves.  `None` unless `0 < first <= second <= size()`.
+    pub fn consistency_proof(&self, first: u64, second: u64) -> Option<Vec<Node>> {
+        let tree = self.tree.read().unwrap_or_else(|e| e.into_inner());
+        let second = usize::try_from(second).ok().filter(|&n| n <= tree.size())?;
+        let first = usize::try_from(first)
+            .ok()
+            .filter(|&m| m > 0 && m <= second)?;
+        Some(tree.subproof(first, 0, second, true))
+    }
+}
+
+#[tracing::instrument(name = "store.append_log", skip_all, fields(path = ?path))]
+fn append_line(path: &Path, entry: &LogEntry) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut line = serde_json::to_vec(entry).expect("log entries serialize");
+    line.push(b'\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(&line)?;
+    file.sync_data()
+}
+
+/// RFC 9162 §2.1.3.2: does `path` place `leaf_hash` at `index` in a tree
+/// of `tree_size` leaves with root `root`?
+pub fn verify_inclusion(
+    leaf_hash: &Node,
+    index: u64,
+    tree_size: u64,
+    path: &[Node],
+    root: &Node,
+) -> bool {
+    if index >= tree_size {
+        return false;
+    }
+    let (mut fn_, mut sn) = (index, tree_size - 1);
+    let mut r = *leaf_hash;
+    for p in path {
+        if sn == 0 {
+            return false;
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            r = node_hash(p, &r);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    sn == 0 && r == *root
+}
+
+/// RFC 9162 §2.1.4.2: does `proof` show the tree of `first` leaves with
+/// root `first_root` is a prefix of the tree of `second` leaves with root
+/// `second_root`?
+pub fn verify_consistency(
+    first: u64,
+    second: u64,
+    first_root: &Node,
+    second_root: &Node,
+    proof: &[Node],
+) -> bool {
+    if first == 0 || first > second {
+        return false;
+    }
+    if first == second {
+        return proof.is_empty() && first_root == second_root;
+    }
+    let mut path = proof.to_vec();
+    if first.is_power_of_two() {
+        path.insert(0, *first_root);
+    }
+    let Some((seed, rest)) = path.split_first() else {
+        return false;
+    };
+    let (mut fn_, mut sn) = (first - 1, second - 1);
+    while fn_ & 1 == 1 {
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    let (mut fr, mut sr) = (*seed, *seed);
+    for c in rest {
+        if sn == 0 {
+            return false;
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    fr == *first_root && sr == *second_root && sn == 0
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ed25519_dalek::{Signature, Verifier};
+
+    fn log_of(n: u64) -> TransparencyLog {
+        let log = TransparencyLog::memory();
+        for i in 0..n {
+            log.append(&format!("nullifier-{i}"), 8000, 1_700_000_000 + i).unwrap();
+        }
+        log
+    }
+
+    #[test]
+    fn every_leaf_proves_inclusion_at_every_size() {
+        let log = log_of(13);
+        for size in 1..=13 {
+            let root = log.root(size).unwrap();
+            for index in 0..size {
+                let (leaf, path) = log.inclusion_proof(index, size).unwrap();
+                assert!(verify_inclusion(&leaf, index, size, &path, &root), "{index}/{size}");
+                assert!(!verify_inclusion(&leaf, index ^ 1, size, &path, &root) || size == 1);
+            }
+        }
+        assert!(log.inclusion_proof(13, 13).is_none());
+        assert!(log.inclusion_proof(0, 14).is_none());
+    }
+
+    #[test]
+    fn every_prefix_is_consistent() {
+        let log = log_of(13);
+        for second in 1..=13 {
+            let second_root = log.root(second).unwrap();
+            for first in 1..=second {
+                let first_root = log.root(first).unwrap();
+                let proof = log.consistency_proof(first, second).unwrap();
+                assert!(
+                    verify_consistency(first, second, &first_root, &second_root, &proof),
+                    "{first}->{second}"
+                );
+            }
+        }
+        assert!(log.consistency_proof(0, 3).is_none());
+        assert!(log.consistency_proof(3, 14).is_none());
+    }
+
+    #[test]
+    fn a_forked_log_is_not_consistent() {
+        let honest = log_of(6);
+        let forked = log_of(4);
+        forked.append("extra-nullifier", 8000, 1).unwrap();
+        forked.append("nullifier-5", 8000, 1_700_000_005).unwrap();
+
+        // Extra leaves can only be appended, where auditors see them.
+        let first_root = honest.root(4).unwrap();
+        let proof = forked.consistency_proof(4, 6).unwrap();
+        assert!(verify_consistency(4, 6, &first_root, &forked.root(6).unwrap(), &proof));
+
+        // Rewriting history is caught against the earlier head.
+        let rewritten = TransparencyLog::memory();
+        for i in 0..6 {
+            let nullifier = if i == 2 { "sybil".to_string() } else { format!("nullifier-{i}") };
+            rewritten.append(&nullifier, 8000, 1_700_000_000 + i).unwrap();
+        }
+        let proof = rewritten.consistency_proof(4, 6).unwrap();
+        assert!(!verify_consistency(4, 6, &first_root, &rewritten.root(6).unwrap(), &proof));
+    }
+
+    #[test]
+    fn matches_rfc_tree_shape() {
+        let log = log_of(3);
+        let leaves: Vec<Node> = log.entries(0, 3).iter().map(LogEntry::leaf_hash).collect();
+        let expected = node_hash(&node_hash(&leaves[0], &leaves[1]), &leaves[2]);
+        assert_eq!(log.root(3), Some(expected));
+        assert_eq!(
+            hex::encode(log_of(0).root(0).unwrap()),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    /// The textbook recursive definition, for checking the cached levels.
+    fn tree_hash(leaves: &[Node]) -> Node {
+        match leaves {
+            [] => Sha256::digest([]).into(),
+            [leaf] => *leaf,
+            _ => {
+                let k = split(leaves.len());
+                node_hash(&tree_hash(&leaves[..k]), &tree_hash(&leaves[k..]))
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_roots_match_the_recursive_definition() {
+        let log = TransparencyLog::memory();
+        let mut leaves = Vec::new();
+        for i in 0..70u64 {
+            assert_eq!(log.root(i), Some(tree_hash(&leaves)), "{i}");
+            leaves.push(log.append(&format!("nullifier-{i}"), 8000, i).unwrap().leaf_hash());
+        }
+        for size in [1, 2, 31, 64, 70] {
+            assert_eq!(log.root(size), Some(tree_hash(&leaves[..size as usize])));
+        }
+        assert!(log.root(71).is_none());
+    }
+
+    #[test]
+    fn tree_head_is_signed_once_per_size_and_key() {
+        let keys = KeyRing::from_seed([3; 32]);
+        let log = log_of(5);
+        let head = log.tree_head(&keys, 1_700_000_100);
+        assert_eq!(log.tree_head(&keys, 1_700_000_200), head);
+
+        log.append("nullifier-5", 8000, 1_700_000_150).unwrap();
+        let grown = log.tree_head(&keys, 1_700_000_300);
+        assert_eq!((grown.tree_size, grown.timestamp), (6, 1_700_000_300));
+
+        let rotated = KeyRing::from_seed([4; 32]);
+        let resigned = log.tree_head(&rotated, 1_700_000_400);
+        assert_eq!(resigned.root_hash, grown.root_hash);
+        assert_ne!(resigned.kid, grown.kid);
+    }
+
+    #[test]
+    fn tree_head_is_signed() {
+        let keys = KeyRing::from_seed([3; 32]);
+        let log = log_of(5);
+        let head = log.tree_head(&keys, 1_700_000_100);
+        assert_eq!(head.tree_size, 5);
+        assert_eq!(head.root_hash, hex::encode(log.root(5).unwrap()));
+        let signature: [u8; 64] = URL_SAFE_NO_PAD
+            .decode(&head.signature)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let message = signing_input(head.tree_size, &head.root_hash, head.timestamp);
+        assert!(keys
+            .verifying_key()
+            .verify(message.as_bytes(), &Signature::from_bytes(&signature))
+            .is_ok());
+    }
+
+    #[test]
+    fn commitments_hide_nullifiers_and_are_findable() {
+        let log = log_of(4);
+        log.append("nullifier-1", 9000, 1_800_000_000).unwrap();
+        let found = log.find(&commitment("nullifier-1"));
+        assert_eq!(found.iter().map(|e| e.index).collect::<Vec<_>>(), vec![1, 4]);
+        assert!(!found[0].commitment.contains("nullifier"));
+        assert_eq!(log.entries(2, 100).len(), 3);
+        assert!(log.entries(5, 9).is_empty());
+    }
+
+    #[test]
+    fn reopened_log_continues() {
+        let dir = std::env::temp_dir().join(format!("transparency-{}", rand::random::<u64>()));
+        let path = dir.join("transparency.ndjson");
+        let log = TransparencyLog::open(&path).unwrap();
+        log.append("nullifier-0", 8000, 1).unwrap();
+        log.append("nullifier-1", 8000, 2).unwrap();
+        let root = log.root(2).unwrap();
+
+        let reopened = TransparencyLog::open(&path).unwrap();
+        assert_eq!(reopened.root(2), Some(root));
+        assert_eq!(reopened.append("nullifier-2", 8000, 3).unwrap().index, 2);
+
+        // A dropped line is refused rather than silently renumbered.
+        let text = fs::read_to_string(&path).unwrap();
+        let without_first: Vec<&str> = text.lines().skip(1).collect();
+        fs::write(&path, without_first.join("\n")).unwrap();
+        assert!(TransparencyLog::open(&path).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
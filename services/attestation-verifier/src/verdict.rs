@@ -0,0 +1,213 @@
+//! Structured trust verdicts.
+//!
+//! Platform verifiers report *signals* (reason codes); the scoring policy
+//! (see `policy.rs`) turns those signals into a `trustScore` and an
+//! assurance level.  Clients keep reading `trustScore`, but can now see why
+//! a device scored what it did and under which policy version.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::Platform;
+
+/// Minimum score for any assurance at all (session creation threshold).
+const BRONZE_MIN_SCORE: f32 = 0.5;
+
+/// Minimum score for Silver (high-impact action threshold).
+const SILVER_MIN_SCORE: f32 = 0.7;
+
+/// LUMA assurance tiers (whitepaper §3).  Gold requires CAPoW hardware and
+/// is deferred past Season 0, so the stub verifiers never reach it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AssuranceLevel {
+    None,
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl AssuranceLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AssuranceLevel::None => "none",
+            AssuranceLevel::Bronze => "bronze",
+            AssuranceLevel::Silver => "silver",
+            AssuranceLevel::Gold => "gold",
+        }
+    }
+}
+
+/// Why a verifier raised or lowered a device's trust.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReasonCode {
+    /// Mock mode (`x-mock-attestation`, `E2E_MODE`, or the E2E test token).
+    MockAttestation,
+    /// Device key is held in secure hardware.
+    HwBackedKey,
+    /// Platform vouched for the calling app's integrity.
+    AppIntegrityOk,
+    /// Mobile token was not recognized by the platform verifier.
+    AttestationUnverified,
+    /// Token looks like it came from an emulator or simulator.
+    EmulatorSuspected,
+    /// Web integrity token is present and plausibly sized.
+    WebTokenPresent,
+    /// Web integrity token is too short to be meaningful.
+    WebTokenTooShort,
+    /// Continuously Attested Proof-of-Work hardware (Gold; deferred).
+    CapowAttested,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Signal {
+    pub code: ReasonCode,
+    /// Signed contribution to the trust score under the active policy.
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Verdict {
+    /// Handle for later policy dry runs; empty until the verdict is recorded.
+    pub id: String,
+    pub trust_score: f32,
+    pub assurance_level: AssuranceLevel,
+    pub signals: Vec<Signal>,
+    pub policy_version: String,
+}
+
+/// Gold needs CAPoW; Silver needs the high-impact score with no negative
+/// signals; Bronze is anything that clears the session threshold.
+pub fn assurance_for(trust_score: f32, signals: &[Signal]) -> AssuranceLevel {
+    let clean = signals.iter().all(|s| s.weight >= 0.0);
+    let has = |code| signals.iter().any(|s| s.code == code);
+    if trust_score < BRONZE_MIN_SCORE {
+        AssuranceLevel::None
+    } else if clean && has(ReasonCode::CapowAttested) {
+        AssuranceLevel::Gold
+    } else if clean && trust_score >= SILVER_MIN_SCORE {
+        AssuranceLevel::Silver
+    } else {
+        AssuranceLevel::Bronze
+    }
+}
+
+// ── verdict log ────────────────────────────────────────────────────────
+
+/// What is kept of an issued verdict: enough to re-score it under another
+/// policy, and nothing that identifies the device.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredVerdict {
+    pub id: String,
+    pub platform: Platform,
+    pub codes: Vec<ReasonCode>,
+    pub trust_score: f32,
+    pub assurance_level: AssuranceLevel,
+    pub policy_version: String,
+    pub issued_at: u64,
+}
+
+/// Bounded in-memory log of recent verdicts (oldest evicted first).
+pub struct VerdictLog {
+    capacity: usize,
+    entries: Mutex<VecDeque<StoredVerdict>>,
+}
+
+impl VerdictLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn record(&self, entry: StoredVerdict) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    pub fn get(&self, id: &str) -> Option<StoredVerdict> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().find(|e| e.id == id).cloned()
+    }
+
+    pub fn snapshot(&self) -> Vec<StoredVerdict> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().cloned().collect()
+    }
+}
+
+// ── tests ──────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(code: ReasonCode, weight: f32) -> Signal {
+        Signal { code, weight }
+    }
+
+    #[test]
+    fn assurance_levels() {
+        assert_eq!(assurance_for(0.0, &[]), AssuranceLevel::None);
+        assert_eq!(
+            assurance_for(0.5, &[signal(ReasonCode::AttestationUnverified, 0.5)]),
+            AssuranceLevel::Bronze
+        );
+        assert_eq!(
+            assurance_for(0.8, &[signal(ReasonCode::WebTokenPresent, 0.8)]),
+            AssuranceLevel::Silver
+        );
+        assert_eq!(
+            assurance_for(1.0, &[signal(ReasonCode::CapowAttested, 1.0)]),
+            AssuranceLevel::Gold
+        );
+    }
+
+    #[test]
+    fn negative_signal_caps_assurance_at_bronze() {
+        let signals = [
+            signal(ReasonCode::HwBackedKey, 0.5),
+            signal(ReasonCode::AppIntegrityOk, 0.5),
+            signal(ReasonCode::EmulatorSuspected, -0.5),
+        ];
+        assert_eq!(assurance_for(0.9, &signals), AssuranceLevel::Bronze);
+    }
+
+    #[test]
+    fn log_evicts_oldest() {
+        let log = VerdictLog::new(2);
+        for i in 0..3 {
+            log.record(StoredVerdict {
+                id: format!("v{i}"),
+                platform: Platform::Web,
+                codes: vec![ReasonCode::WebTokenPresent],
+                trust_score: 0.8,
+                assurance_level: AssuranceLevel::Silver,
+                policy_version: "test".to_string(),
+                issued_at: i,
+            });
+        }
+        assert!(log.get("v0").is_none());
+        assert_eq!(log.get("v2").unwrap().issued_at, 2);
+        assert_eq!(log.snapshot().len(), 2);
+    }
+
+    #[test]
+    fn reason_codes_serialize_screaming_snake() {
+        let json = serde_json::to_string(&ReasonCode::HwBackedKey).unwrap();
+        assert_eq!(json, "\"HW_BACKED_KEY\"");
+        let json = serde_json::to_string(&AssuranceLevel::Silver).unwrap();
+        assert_eq!(json, "\"silver\"");
+    }
+}
@@ -0,0 +1,59 @@
+//! The public router, driven the way an embedding service would.
+
+use attestation_verifier::budget::BudgetLedger;
+use attestation_verifier::district::DistrictHasher;
+use attestation_verifier::keys::KeyRing;
+use attestation_verifier::policy::{PolicyStore, ScoringPolicy};
+use attestation_verifier::residency::{self, RootRegistry};
+use attestation_verifier::{router, session, AppState, Config, SessionResponse};
+use warp::http::StatusCode;
+use warp::test::request;
+
+fn state() -> AppState {
+    AppState::new(
+        PolicyStore::new(ScoringPolicy::builtin()),
+        100,
+        KeyRing::generate(),
+        RootRegistry::new(Vec::new(), residency::DEFAULT_MAX_ROOT_AGE_SECS),
+        DistrictHasher::dev(),
+        BudgetLedger::memory(),
+    )
+}
+
+#[tokio::test]
+async fn embedded_router_issues_verifiable_sessions() {
+    let state = state();
+    let routes = router(&Config::default(), state.clone());
+
+    let res = request()
+        .method("POST")
+        .path("/verify")
+        .json(&serde_json::json!({
+            "platform": "web",
+            "integrityToken": "long-enough-token",
+            "deviceKey": "dGVzdC1kZXZpY2Uta2V5",
+            "nonce": "a1b2c3d4e5f60718"
+        }))
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().contains_key("x-request-id"));
+    let session: SessionResponse = serde_json::from_slice(res.body()).unwrap();
+    let claims = session::verify(&state.keys.current(), &session.token, session.created_at / 1000)
+        .unwrap();
+    assert_eq!(claims.sub, session.nullifier);
+    assert_eq!(state.transparency.size(), 1);
+}
+
+#[tokio::test]
+async fn embedded_router_handles_rejections_and_metrics() {
+    let routes = router(&Config::default(), state());
+
+    let res = request().method("GET").path("/nope").reply(&routes).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = request().method("GET").path("/metrics").reply(&routes).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = String::from_utf8_lossy(res.body());
+    assert!(body.contains("route=\"unmatched\""));
+}